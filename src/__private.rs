@@ -0,0 +1,993 @@
+//! Implementation details of the macros.
+//!
+//! Nothing in this module is part of the public API: items are only `pub` so that
+//! macro expansions in downstream crates can reach them through `$crate::__private`.
+
+use core::cmp::Ordering;
+use core::iter::Sum;
+use core::mem::MaybeUninit;
+use core::ops::{Add, Div};
+use core::ptr;
+
+#[cfg(feature = "alloc")]
+use alloc::{
+    borrow::{Cow, ToOwned},
+    collections::{BTreeMap, BinaryHeap},
+};
+#[cfg(feature = "alloc")]
+pub use alloc::{collections::VecDeque, string::String, vec::Vec};
+#[cfg(feature = "alloc")]
+use core::cmp::Reverse;
+#[cfg(feature = "alloc")]
+use core::fmt::{Display, Write};
+#[cfg(feature = "alloc")]
+use core::{slice, str::Chars};
+
+#[cfg(feature = "std")]
+use std::any::Any;
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, DefaultHasher, Hash, Hasher};
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::num::NonZeroUsize;
+#[cfg(feature = "std")]
+use std::sync::mpsc::{self, Receiver};
+#[cfg(feature = "std")]
+use std::thread::{self, LocalKey};
+
+/// Turn the source of a `for` clause into an iterator.
+///
+/// Like in Python's `for x in seq`, any [`IntoIterator`] is accepted, so collections can be
+/// iterated without calling `iter` or `into_iter`. Lending iterators are kept unchanged.
+pub trait Source {
+    /// The iterator over the items of the source.
+    type Iter;
+
+    /// Return the iterator over the items of the source.
+    fn into_source(self) -> Self::Iter;
+}
+
+impl<I: IntoIterator> Source for I {
+    type Iter = I::IntoIter;
+
+    #[inline(always)]
+    fn into_source(self) -> Self::Iter {
+        self.into_iter()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> Source for crate::lending::Lines<R> {
+    type Iter = Self;
+
+    #[inline(always)]
+    fn into_source(self) -> Self::Iter {
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> Source for crate::lending::StopAtError<R> {
+    type Iter = Self;
+
+    #[inline(always)]
+    fn into_source(self) -> Self::Iter {
+        self
+    }
+}
+
+/// Collect `iter` into an array, panicking if it does not have exactly `N` items.
+pub fn array<I: Iterator, const N: usize>(mut iter: I) -> [I::Item; N] {
+    let array = core::array::from_fn(|_| {
+        iter.next()
+            .expect("comprehension has fewer values than the length of the array")
+    });
+    assert!(
+        iter.next().is_none(),
+        "comprehension has more values than the length of the array"
+    );
+    array
+}
+
+/// Array filled in order by `array![const; ...]`, without closures, so that it can be used
+/// in constant contexts.
+pub struct ArrayBuilder<T, const N: usize> {
+    array: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> ArrayBuilder<T, N> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self {
+            array: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+
+    /// Return the number of values pushed so far, which is the index of the next one.
+    pub const fn next_index(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Append `value`, panicking if the array is already full.
+    pub const fn push(&mut self, value: T) {
+        assert!(self.len < N, "array is already full");
+        self.array[self.len] = MaybeUninit::new(value);
+        self.len += 1;
+    }
+
+    /// Return the array, panicking if it is not full.
+    pub const fn finish(self) -> [T; N] {
+        assert!(self.is_full(), "array is not full");
+        // SAFETY: all the `N` values were initialized by `push`, and `MaybeUninit<T>` has
+        // the same layout as `T`.
+        unsafe { ptr::read((&raw const self.array).cast::<[T; N]>()) }
+    }
+}
+
+/// Return `iter` unchanged, pinning the type of its items to `T`.
+#[inline(always)]
+pub fn typed<T, I>(iter: I) -> I
+where
+    I: Iterator<Item = T>,
+{
+    iter
+}
+
+#[cfg(feature = "alloc")]
+/// Unzip an iterator of triplets into three vectors.
+///
+/// Same as [`Iterator::unzip`], but for 3-tuples.
+pub fn unzip3<I, A, B, C>(iter: I) -> (Vec<A>, Vec<B>, Vec<C>)
+where
+    I: Iterator<Item = (A, B, C)>,
+{
+    let (lower, _) = iter.size_hint();
+    let mut a = Vec::with_capacity(lower);
+    let mut b = Vec::with_capacity(lower);
+    let mut c = Vec::with_capacity(lower);
+
+    for (x, y, z) in iter {
+        a.push(x);
+        b.push(y);
+        c.push(z);
+    }
+
+    (a, b, c)
+}
+
+#[cfg(feature = "std")]
+/// Return the number of threads available to [`par_chunks`].
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+#[cfg(feature = "std")]
+/// Split the items of `iter` into at most `threads` chunks, apply `f` to each chunk in
+/// parallel, and concatenate the results in the original order.
+pub fn par_chunks<I, T, U, F>(iter: I, threads: usize, f: F) -> Vec<U>
+where
+    I: IntoIterator<Item = T>,
+    T: Send,
+    U: Send,
+    F: Fn(Vec<T>) -> Vec<U> + Sync,
+{
+    let items: Vec<T> = iter.into_iter().collect();
+    let threads = threads.max(1);
+    let chunk_size = items.len().div_ceil(threads).max(1);
+
+    let mut items = items.into_iter();
+    let mut chunks = Vec::with_capacity(threads);
+    loop {
+        let chunk: Vec<T> = items.by_ref().take(chunk_size).collect();
+        if chunk.is_empty() {
+            break;
+        }
+        chunks.push(chunk);
+    }
+
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .into_iter()
+            .map(|chunk| scope.spawn(move || f(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|err| std::panic::resume_unwind(err))
+            })
+            .collect()
+    })
+}
+
+#[cfg(feature = "std")]
+/// Consume `iter` on a spawned thread, sending its items through a channel holding
+/// at most `bound` items, and return the receiving end.
+pub fn channel<I>(iter: I, bound: usize) -> Receiver<I::Item>
+where
+    I: Iterator + Send + 'static,
+    I::Item: Send + 'static,
+{
+    let (sender, receiver) = mpsc::sync_channel(bound);
+    thread::spawn(move || {
+        for item in iter {
+            if sender.send(item).is_err() {
+                // Receiver was dropped, nobody is listening anymore
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(feature = "std")]
+/// Write each item of `iter` to `writer` on its own line, stopping at the first error.
+pub fn write_lines<W, I>(mut writer: W, iter: I) -> io::Result<()>
+where
+    W: io::Write,
+    I: Iterator,
+    I::Item: Display,
+{
+    for item in iter {
+        writer.write_fmt(format_args!("{item}\n"))?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+/// Type-erased storage used by `cached_vect!`, indexed by the hash of the keys.
+pub type Cache = HashMap<u64, Vec<(Box<dyn Any>, Box<dyn Any>)>>;
+
+#[cfg(feature = "std")]
+/// Return the value cached for `key`, or compute it with `f` and cache it.
+///
+/// Entries are only hits if both the key and the value have the expected types,
+/// so the same cache can safely be shared by several monomorphizations of a call site.
+pub fn cached<K, T, F>(cache: &'static LocalKey<RefCell<Cache>>, key: K, f: F) -> T
+where
+    K: Hash + Eq + 'static,
+    T: Clone + 'static,
+    F: FnOnce() -> T,
+{
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    let hit = cache.with_borrow(|cache| {
+        cache.get(&hash)?.iter().find_map(|(k, v)| {
+            if k.downcast_ref::<K>() == Some(&key) {
+                v.downcast_ref::<T>().cloned()
+            } else {
+                None
+            }
+        })
+    });
+    if let Some(value) = hit {
+        return value;
+    }
+
+    // The cache is not borrowed while computing the value, so `f` may use it too
+    let value = f();
+    cache.with_borrow_mut(|cache| {
+        cache
+            .entry(hash)
+            .or_default()
+            .push((Box::new(key), Box::new(value.clone())))
+    });
+    value
+}
+
+/// Collect `iter` into a `VecDeque` by pushing each item to the front, reversing its order.
+#[cfg(feature = "alloc")]
+pub fn deque_front<I: Iterator>(iter: I) -> VecDeque<I::Item> {
+    let mut deque = VecDeque::with_capacity(iter.size_hint().0);
+    for item in iter {
+        deque.push_front(item);
+    }
+    deque
+}
+
+#[cfg(feature = "alloc")]
+/// Return the `n` largest items of `iter` in descending order, keeping at most `n` items
+/// in a heap. Of equal items, the first ones are kept.
+pub fn nlargest<I>(n: usize, iter: I) -> Vec<I::Item>
+where
+    I: Iterator,
+    I::Item: Ord,
+{
+    let mut heap = BinaryHeap::new();
+    for item in iter {
+        if heap.len() < n {
+            heap.push(Reverse(item));
+        } else if let Some(mut smallest) = heap.peek_mut() {
+            if item > smallest.0 {
+                *smallest = Reverse(item);
+            }
+        }
+    }
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(item)| item)
+        .collect()
+}
+
+#[cfg(feature = "alloc")]
+/// Return the `n` smallest items of `iter` in ascending order, keeping at most `n` items
+/// in a heap. Of equal items, the first ones are kept.
+pub fn nsmallest<I>(n: usize, iter: I) -> Vec<I::Item>
+where
+    I: Iterator,
+    I::Item: Ord,
+{
+    let mut heap = BinaryHeap::new();
+    for item in iter {
+        if heap.len() < n {
+            heap.push(item);
+        } else if let Some(mut largest) = heap.peek_mut() {
+            if item < *largest {
+                *largest = item;
+            }
+        }
+    }
+    heap.into_sorted_vec()
+}
+
+/// Return the first minimum and the last maximum of `iter`, computed in a single pass.
+pub fn minmax<I>(mut iter: I) -> Option<(I::Item, I::Item)>
+where
+    I: Iterator,
+    I::Item: Ord + Clone,
+{
+    let first = iter.next()?;
+    Some(iter.fold((first.clone(), first), |(min, max), x| {
+        if x < min {
+            (x, max)
+        } else if x >= max {
+            (min, x)
+        } else {
+            (min, max)
+        }
+    }))
+}
+
+/// Return the index of the first maximum of `iter`.
+pub fn argmax<I>(iter: I) -> Option<usize>
+where
+    I: Iterator,
+    I::Item: Ord,
+{
+    iter.enumerate()
+        .reduce(|best, x| if x.1 > best.1 { x } else { best })
+        .map(|(i, _)| i)
+}
+
+/// Return the index of the first minimum of `iter`.
+pub fn argmin<I>(iter: I) -> Option<usize>
+where
+    I: Iterator,
+    I::Item: Ord,
+{
+    iter.enumerate()
+        .reduce(|best, x| if x.1 < best.1 { x } else { best })
+        .map(|(i, _)| i)
+}
+
+/// Floating-point types supported by the float-specific macros.
+pub trait Float: Copy + Add<Output = Self> + Div<Output = Self> + Sum {
+    const ZERO: Self;
+    fn from_usize(n: usize) -> Self;
+    fn is_nan(self) -> bool;
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+macro_rules! impl_float {
+    ($($t:ty),*) => {
+        $(
+        impl Float for $t {
+            const ZERO: Self = 0.0;
+            #[inline]
+            fn from_usize(n: usize) -> Self {
+                n as $t
+            }
+            #[inline]
+            fn is_nan(self) -> bool {
+                <$t>::is_nan(self)
+            }
+            #[inline]
+            fn total_cmp(&self, other: &Self) -> Ordering {
+                <$t>::total_cmp(self, other)
+            }
+        }
+        )*
+    };
+}
+
+impl_float!(f32, f64);
+
+/// Conversion of primitive numbers to floats, with the semantics of `as`.
+///
+/// Unlike [`Into`], this is implemented for all primitive numbers, including those that
+/// cannot be converted to floats without loss, like `u64`, `i64` or `usize`.
+pub trait IntoFloat<F> {
+    fn into_float(self) -> F;
+}
+
+macro_rules! impl_into_float {
+    ($($t:ty),*) => {
+        $(
+        impl IntoFloat<f32> for $t {
+            #[inline]
+            fn into_float(self) -> f32 {
+                self as f32
+            }
+        }
+        impl IntoFloat<f64> for $t {
+            #[inline]
+            fn into_float(self) -> f64 {
+                self as f64
+            }
+        }
+        )*
+    };
+}
+
+impl_into_float!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+/// Return the maximum of the non-NaN floats of `iter`.
+pub fn fmax<I, T>(iter: I) -> Option<T>
+where
+    I: Iterator<Item = T>,
+    T: Float,
+{
+    iter.filter(|x| !x.is_nan()).max_by(Float::total_cmp)
+}
+
+/// Return the minimum of the non-NaN floats of `iter`.
+pub fn fmin<I, T>(iter: I) -> Option<T>
+where
+    I: Iterator<Item = T>,
+    T: Float,
+{
+    iter.filter(|x| !x.is_nan()).min_by(Float::total_cmp)
+}
+
+/// Return the sum of the non-NaN floats of `iter`.
+pub fn nansum<I, T>(iter: I) -> T
+where
+    I: Iterator<Item = T>,
+    T: Float,
+{
+    iter.filter(|x| !x.is_nan()).sum()
+}
+
+/// Return the sum of the non-NaN floats of `iter`, and the number of NaN values skipped.
+pub fn nansum_counted<I, T>(iter: I) -> (T, usize)
+where
+    I: Iterator<Item = T>,
+    T: Float,
+{
+    let mut skipped = 0;
+    let sum = iter
+        .filter(|x| {
+            skipped += usize::from(x.is_nan());
+            !x.is_nan()
+        })
+        .sum();
+    (sum, skipped)
+}
+
+/// Return the mean of the values of `iter` as floats, computed in a single pass.
+pub fn mean<I, F>(iter: I) -> Option<F>
+where
+    I: Iterator,
+    I::Item: IntoFloat<F>,
+    F: Float,
+{
+    let (sum, count) = iter.fold((F::ZERO, 0), |(sum, count), x| {
+        (sum + x.into_float(), count + 1)
+    });
+    (count > 0).then(|| sum / F::from_usize(count))
+}
+
+/// Return the mean of the non-NaN floats of `iter`, computed in a single pass.
+pub fn nanmean<I, T>(iter: I) -> Option<T>
+where
+    I: Iterator<Item = T>,
+    T: Float,
+{
+    let (sum, count) = iter
+        .filter(|x| !x.is_nan())
+        .fold((T::ZERO, 0), |(sum, count), x| (sum + x, count + 1));
+    (count > 0).then(|| sum / T::from_usize(count))
+}
+
+/// Return the mean of the non-NaN floats of `iter`, and the number of NaN values skipped.
+pub fn nanmean_counted<I, T>(iter: I) -> (Option<T>, usize)
+where
+    I: Iterator<Item = T>,
+    T: Float,
+{
+    let (sum, count, skipped) = iter.fold((T::ZERO, 0, 0), |(sum, count, skipped), x| {
+        if x.is_nan() {
+            (sum, count, skipped + 1)
+        } else {
+            (sum + x, count + 1, skipped)
+        }
+    });
+    ((count > 0).then(|| sum / T::from_usize(count)), skipped)
+}
+
+#[cfg(feature = "std")]
+/// Aggregate the values of `iter` in a two-level map indexed by row, then by column.
+pub fn pivot<I, R, C, V, A, F>(iter: I, mut agg: F) -> HashMap<R, HashMap<C, A>>
+where
+    I: Iterator<Item = (R, C, V)>,
+    R: Hash + Eq,
+    C: Hash + Eq,
+    F: FnMut(Option<A>, V) -> A,
+{
+    let mut table: HashMap<R, HashMap<C, A>> = HashMap::new();
+    for (row, col, value) in iter {
+        let cells = table.entry(row).or_default();
+        let acc = cells.remove(&col);
+        cells.insert(col, agg(acc, value));
+    }
+    table
+}
+
+/// Aggregation functions available in `pivot!`.
+#[cfg(feature = "std")]
+pub mod pivot_agg {
+    use core::ops::Add;
+
+    pub fn sum<T: Add<Output = T>>(acc: Option<T>, value: T) -> T {
+        match acc {
+            Some(acc) => acc + value,
+            None => value,
+        }
+    }
+
+    pub fn count<T>(acc: Option<usize>, _value: T) -> usize {
+        acc.unwrap_or(0) + 1
+    }
+
+    pub fn min<T: Ord>(acc: Option<T>, value: T) -> T {
+        match acc {
+            Some(acc) => acc.min(value),
+            None => value,
+        }
+    }
+
+    pub fn max<T: Ord>(acc: Option<T>, value: T) -> T {
+        match acc {
+            Some(acc) => acc.max(value),
+            None => value,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// Count the occurrences of each item of `iter`.
+pub fn counter<I, S>(iter: I) -> HashMap<I::Item, usize, S>
+where
+    I: Iterator,
+    I::Item: Hash + Eq,
+    S: BuildHasher + Default,
+{
+    let mut counts = HashMap::default();
+    for item in iter {
+        *counts.entry(item).or_default() += 1;
+    }
+    counts
+}
+
+#[cfg(feature = "std")]
+/// Bucket the values of `iter` by key.
+pub fn group_by<I, K, V>(iter: I) -> HashMap<K, Vec<V>>
+where
+    I: Iterator<Item = (K, V)>,
+    K: Hash + Eq,
+{
+    let mut groups: HashMap<K, Vec<V>> = HashMap::new();
+    for (key, value) in iter {
+        groups.entry(key).or_default().push(value);
+    }
+    groups
+}
+
+#[cfg(feature = "std")]
+/// Maps that [`dict`](macro@crate::dict) collects into when duplicate keys are handled.
+pub trait Map<K, V>: Default {
+    /// Return `true` if `key` has a value.
+    fn contains_key(&self, key: &K) -> bool;
+
+    /// Set the value of `key`.
+    fn insert(&mut self, key: K, value: V);
+
+    /// Remove and return the value of `key`, if any.
+    fn remove(&mut self, key: &K) -> Option<V>;
+}
+
+#[cfg(feature = "std")]
+impl<K: Hash + Eq, V, S: BuildHasher + Default> Map<K, V> for HashMap<K, V, S> {
+    fn contains_key(&self, key: &K) -> bool {
+        self.contains_key(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Ord, V> Map<K, V> for BTreeMap<K, V> {
+    fn contains_key(&self, key: &K) -> bool {
+        self.contains_key(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+}
+
+#[cfg(feature = "std")]
+/// Collect the pairs of `iter`, keeping the first value of each key.
+pub fn dict_first<M, K, V, I>(iter: I) -> M
+where
+    M: Map<K, V>,
+    I: Iterator<Item = (K, V)>,
+{
+    let mut map = M::default();
+    for (key, value) in iter {
+        if !map.contains_key(&key) {
+            map.insert(key, value);
+        }
+    }
+    map
+}
+
+#[cfg(feature = "std")]
+/// Collect the pairs of `iter`, failing with the first key that is seen twice.
+pub fn dict_unique<M, K, V, I>(iter: I) -> Result<M, K>
+where
+    M: Map<K, V>,
+    I: Iterator<Item = (K, V)>,
+{
+    let mut map = M::default();
+    for (key, value) in iter {
+        if map.contains_key(&key) {
+            return Err(key);
+        }
+        map.insert(key, value);
+    }
+    Ok(map)
+}
+
+#[cfg(feature = "std")]
+/// Collect the pairs of `iter`, merging the values of each key with `merge`, in order.
+pub fn dict_merge<M, K, V, I, F>(iter: I, mut merge: F) -> M
+where
+    M: Map<K, V>,
+    I: Iterator<Item = (K, V)>,
+    F: FnMut(V, V) -> V,
+{
+    let mut map = M::default();
+    for (key, value) in iter {
+        let value = match map.remove(&key) {
+            Some(previous) => merge(previous, value),
+            None => value,
+        };
+        map.insert(key, value);
+    }
+    map
+}
+
+#[cfg(feature = "alloc")]
+/// Bucket the values of `iter` by key, in key order.
+pub fn group_by_ordered<I, K, V>(iter: I) -> BTreeMap<K, Vec<V>>
+where
+    I: Iterator<Item = (K, V)>,
+    K: Ord,
+{
+    let mut groups: BTreeMap<K, Vec<V>> = BTreeMap::new();
+    for (key, value) in iter {
+        groups.entry(key).or_default().push(value);
+    }
+    groups
+}
+
+#[cfg(feature = "alloc")]
+/// Return the items of the `page`-th window of `size` items of `iter`, and whether
+/// any item remains after it.
+pub fn paginate<I: Iterator>(iter: I, page: usize, size: usize) -> (Vec<I::Item>, bool) {
+    let mut iter = iter.skip(page.saturating_mul(size));
+    let items = iter.by_ref().take(size).collect();
+    (items, iter.next().is_some())
+}
+
+#[cfg(feature = "std")]
+/// Drop the items of `iter` equal to a previous one.
+pub fn unique<I>(iter: I) -> impl Iterator<Item = I::Item>
+where
+    I: Iterator,
+    I::Item: Hash + Eq + Clone,
+{
+    let mut seen = HashSet::new();
+    iter.filter(move |item| seen.insert(item.clone()))
+}
+
+#[cfg(feature = "alloc")]
+/// Collect the items of each batch of `iter` into a vector, stopping at the first error.
+pub fn try_flat_collect<I, B, E>(iter: I) -> Result<Vec<B::Item>, E>
+where
+    I: Iterator<Item = Result<B, E>>,
+    B: IntoIterator,
+{
+    let mut collection = Vec::new();
+    for batch in iter {
+        collection.extend(batch?);
+    }
+    Ok(collection)
+}
+
+#[cfg(feature = "std")]
+/// Pair the items of `left` and `right` with equal keys, using a hash index of `right`.
+///
+/// Items of `left` are processed lazily, in order, and items of `right` with the same key
+/// keep their original order.
+pub fn hash_join<L, R, K, FL, FR>(
+    left: L,
+    right: R,
+    left_key: FL,
+    mut right_key: FR,
+) -> HashJoin<L::IntoIter, R::Item, K, FL>
+where
+    L: IntoIterator,
+    R: IntoIterator,
+    L::Item: Clone,
+    R::Item: Clone,
+    K: Hash + Eq,
+    FL: FnMut(&L::Item) -> K,
+    FR: FnMut(&R::Item) -> K,
+{
+    let mut index: HashMap<K, Vec<R::Item>> = HashMap::new();
+    for b in right {
+        index.entry(right_key(&b)).or_default().push(b);
+    }
+    HashJoin {
+        left: left.into_iter(),
+        index,
+        left_key,
+        current: None,
+    }
+}
+
+#[cfg(feature = "std")]
+/// Iterator returned by [`hash_join`], which clones every matching item of the right side.
+pub struct HashJoin<L: Iterator, V, K, F> {
+    left: L,
+    index: HashMap<K, Vec<V>>,
+    left_key: F,
+    current: Option<(L::Item, K, usize)>,
+}
+
+#[cfg(feature = "std")]
+impl<L, V, K, F> Iterator for HashJoin<L, V, K, F>
+where
+    L: Iterator,
+    L::Item: Clone,
+    V: Clone,
+    K: Hash + Eq,
+    F: FnMut(&L::Item) -> K,
+{
+    type Item = (L::Item, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((a, key, position)) = &mut self.current {
+                let matches: &[V] = self.index.get(key).map_or(&[], Vec::as_slice);
+                if let Some(b) = matches.get(*position) {
+                    let b = b.clone();
+                    *position += 1;
+                    if *position < matches.len() {
+                        return Some((a.clone(), b));
+                    }
+                    // The last match takes the left item
+                    let (a, _, _) = self.current.take()?;
+                    return Some((a, b));
+                }
+            }
+            let a = self.left.next()?;
+            let key = (self.left_key)(&a);
+            self.current = Some((a, key, 0));
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// Sources that `cow_vect!` can borrow from, as long as the comprehension leaves them unchanged.
+pub trait CowSource: ToOwned {
+    type Item;
+    type Iter<'a>: Iterator
+    where
+        Self: 'a;
+
+    fn cow_iter(&self) -> Self::Iter<'_>;
+
+    /// Borrow the longest prefix of `self` that `iter` reproduces, and only allocate
+    /// once an item differs from the source.
+    fn cow_collect<I: Iterator<Item = Self::Item>>(&self, iter: I) -> Cow<'_, Self>;
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Clone + PartialEq> CowSource for [T] {
+    type Item = T;
+    type Iter<'a>
+        = slice::Iter<'a, T>
+    where
+        T: 'a;
+
+    fn cow_iter(&self) -> Self::Iter<'_> {
+        self.iter()
+    }
+
+    fn cow_collect<I: Iterator<Item = T>>(&self, mut iter: I) -> Cow<'_, Self> {
+        let mut len = 0;
+        while let Some(item) = iter.next() {
+            if self.get(len) == Some(&item) {
+                len += 1;
+                continue;
+            }
+            let mut owned = self[..len].to_vec();
+            owned.push(item);
+            owned.extend(iter);
+            return Cow::Owned(owned);
+        }
+        Cow::Borrowed(&self[..len])
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl CowSource for str {
+    type Item = char;
+    type Iter<'a> = Chars<'a>;
+
+    fn cow_iter(&self) -> Self::Iter<'_> {
+        self.chars()
+    }
+
+    fn cow_collect<I: Iterator<Item = char>>(&self, mut iter: I) -> Cow<'_, Self> {
+        let mut len = 0;
+        while let Some(c) = iter.next() {
+            if self[len..].starts_with(c) {
+                len += c.len_utf8();
+                continue;
+            }
+            let mut owned = self[..len].to_owned();
+            owned.push(c);
+            owned.extend(iter);
+            return Cow::Owned(owned);
+        }
+        Cow::Borrowed(&self[..len])
+    }
+}
+
+#[cfg(feature = "std")]
+/// Nodes already visited by a [`Walk`], if cycles must be detected.
+pub trait Seen<N> {
+    /// Mark `node` as visited, returning whether it is the first visit.
+    fn insert(&mut self, node: &N) -> bool;
+}
+
+#[cfg(feature = "std")]
+impl<N> Seen<N> for () {
+    #[inline]
+    fn insert(&mut self, _node: &N) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "std")]
+impl<N: Hash + Eq + Clone> Seen<N> for HashSet<N> {
+    fn insert(&mut self, node: &N) -> bool {
+        HashSet::insert(self, node.clone())
+    }
+}
+
+#[cfg(feature = "std")]
+/// Depth-first (preorder) or breadth-first traversal of the nodes reachable from a root.
+pub struct Walk<N, S, F> {
+    pending: VecDeque<N>,
+    seen: S,
+    bfs: bool,
+    children: F,
+}
+
+#[cfg(feature = "std")]
+impl<N, S, F> Walk<N, S, F> {
+    pub fn new<I>(root: N, seen: S, bfs: bool, children: F) -> Self
+    where
+        F: FnMut(N) -> I,
+        I: IntoIterator<Item = N>,
+    {
+        Self {
+            pending: VecDeque::from([root]),
+            seen,
+            bfs,
+            children,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<N, S, F, I> Iterator for Walk<N, S, F>
+where
+    N: Clone,
+    S: Seen<N>,
+    F: FnMut(N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    type Item = N;
+
+    fn next(&mut self) -> Option<N> {
+        loop {
+            let node = if self.bfs {
+                self.pending.pop_front()?
+            } else {
+                self.pending.pop_back()?
+            };
+            if !self.seen.insert(&node) {
+                continue;
+            }
+            let children = (self.children)(node.clone());
+            if self.bfs {
+                self.pending.extend(children);
+            } else {
+                // Children are pushed in reverse, so the first one is visited first
+                let children: Vec<N> = children.into_iter().collect();
+                self.pending.extend(children.into_iter().rev());
+            }
+            return Some(node);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// Format the items of `iter` into a single `String`, separated by `sep`.
+pub fn join_display<I, S>(iter: I, sep: S) -> String
+where
+    I: Iterator,
+    I::Item: Display,
+    S: Display,
+{
+    let mut out = String::new();
+    for (i, item) in iter.enumerate() {
+        // Writing to a `String` cannot fail
+        if i > 0 {
+            let _ = write!(out, "{sep}");
+        }
+        let _ = write!(out, "{item}");
+    }
+    out
+}
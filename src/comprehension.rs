@@ -0,0 +1,104 @@
+//! The iterator type returned by comprehensions.
+
+use core::fmt;
+use core::iter::FusedIterator;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+/// Iterator returned by [`iter`](macro@iter) and the macros based on it.
+///
+/// Comprehensions expand to chains of adaptors, whose types cannot be written down. This
+/// type wraps them into a single named type, so a comprehension can be stored in a struct
+/// field or returned from a function, e.g., as `Comprehension<impl Iterator<Item = T>>`
+/// or, after [`boxed`](Comprehension::boxed), as `Comprehension<Box<dyn Iterator<Item = T>>>`.
+///
+/// # Example
+///
+#[cfg_attr(feature = "alloc", doc = "```rust")]
+#[cfg_attr(not(feature = "alloc"), doc = "```ignore")]
+/// use comptools::*;
+///
+/// struct Squares {
+///     iter: Comprehension<Box<dyn Iterator<Item = u64>>>,
+/// }
+///
+/// let squares = Squares { iter: iter![x * x; for x in 1..4].boxed() };
+/// assert_eq!(squares.iter.collect::<Vec<_>>(), vec![1, 4, 9]);
+/// ```
+#[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct Comprehension<I> {
+    iter: I,
+}
+
+impl<I: Iterator> Comprehension<I> {
+    /// Wrap an iterator.
+    pub fn new(iter: I) -> Self {
+        Self { iter }
+    }
+
+    /// Return the wrapped iterator.
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+
+    /// Box the wrapped iterator, so that its type can be named.
+    #[cfg(feature = "alloc")]
+    pub fn boxed<'a>(self) -> Comprehension<Box<dyn Iterator<Item = I::Item> + 'a>>
+    where
+        I: 'a,
+    {
+        Comprehension::new(Box::new(self.iter))
+    }
+}
+
+impl<I> fmt::Debug for Comprehension<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Comprehension").finish_non_exhaustive()
+    }
+}
+
+impl<I: Iterator> Iterator for Comprehension<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.iter.nth(n)
+    }
+
+    fn fold<B, F>(self, init: B, f: F) -> B
+    where
+        F: FnMut(B, Self::Item) -> B,
+    {
+        self.iter.fold(init, f)
+    }
+}
+
+impl<I: DoubleEndedIterator> DoubleEndedIterator for Comprehension<I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back()
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        self.iter.nth_back(n)
+    }
+
+    fn rfold<B, F>(self, init: B, f: F) -> B
+    where
+        F: FnMut(B, Self::Item) -> B,
+    {
+        self.iter.rfold(init, f)
+    }
+}
+
+impl<I: ExactSizeIterator> ExactSizeIterator for Comprehension<I> {}
+
+impl<I: FusedIterator> FusedIterator for Comprehension<I> {}
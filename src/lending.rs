@@ -0,0 +1,265 @@
+//! Lending iterators, whose items may borrow from the iterator itself.
+//!
+//! The [`Iterator`] trait cannot express sources that reuse an internal buffer, e.g., to read
+//! lines without allocating a new `String` for each of them. A [`LendingIterator`] can, and
+//! it can be used as the source of a comprehension as long as the expression does not keep
+//! borrowing the item: the comprehension is then a regular [`Iterator`].
+//!
+//! Only the variants mapping, filtering, or stopping on the items are supported, and
+//! [`LendingIterator`] must be in scope. As other sources are turned into iterators with
+//! [`IntoIterator`], only the lending iterators of this module are accepted as sources.
+//!
+//! # Example
+//!
+//! ```rust
+//! use comptools::*;
+//! use comptools::lending::{lines, LendingIterator};
+//!
+//! let text = "1\n22\n\n333\n";
+//! // Each line borrows the same buffer, only the lengths are allocated
+//! let lengths: Vec<usize> = vect![line.len(); for line in lines(text.as_bytes()).stop_at_error(); if !line.is_empty()];
+//! assert_eq!(lengths, vec![1, 2, 3]);
+//! ```
+
+use std::io::{self, BufRead};
+
+/// An iterator whose items may borrow from the iterator itself.
+///
+/// Items must therefore be dropped before requesting the next one.
+///
+/// Only the lending iterators of this module can be used as sources of a comprehension.
+/// Other implementations of this trait cannot, but they can first be turned into a regular
+/// [`Iterator`] with [`map`](LendingIterator::map), [`filter_map`](LendingIterator::filter_map)
+/// or [`map_while`](LendingIterator::map_while).
+pub trait LendingIterator {
+    /// The type of the items, borrowing from the iterator for `'a`.
+    type Item<'a>
+    where
+        Self: 'a;
+
+    /// Advance the iterator and return the next item.
+    fn next(&mut self) -> Option<Self::Item<'_>>;
+
+    /// Turn the items into owned values, returning a regular [`Iterator`].
+    fn map<B, F>(self, f: F) -> Map<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(Self::Item<'_>) -> B,
+    {
+        Map { iter: self, f }
+    }
+
+    /// Turn the items into owned values, skipping those mapped to `None`.
+    fn filter_map<B, F>(self, f: F) -> FilterMap<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(Self::Item<'_>) -> Option<B>,
+    {
+        FilterMap { iter: self, f }
+    }
+
+    /// Turn the items into owned values, stopping at the first one mapped to `None`.
+    fn map_while<B, F>(self, f: F) -> MapWhile<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(Self::Item<'_>) -> Option<B>,
+    {
+        MapWhile {
+            iter: self,
+            f,
+            done: false,
+        }
+    }
+}
+
+/// Iterator returned by [`LendingIterator::map`].
+#[derive(Clone, Debug)]
+pub struct Map<I, F> {
+    iter: I,
+    f: F,
+}
+
+impl<I, B, F> Iterator for Map<I, F>
+where
+    I: LendingIterator,
+    F: FnMut(I::Item<'_>) -> B,
+{
+    type Item = B;
+
+    fn next(&mut self) -> Option<B> {
+        self.iter.next().map(&mut self.f)
+    }
+}
+
+/// Iterator returned by [`LendingIterator::filter_map`].
+#[derive(Clone, Debug)]
+pub struct FilterMap<I, F> {
+    iter: I,
+    f: F,
+}
+
+impl<I, B, F> Iterator for FilterMap<I, F>
+where
+    I: LendingIterator,
+    F: FnMut(I::Item<'_>) -> Option<B>,
+{
+    type Item = B;
+
+    fn next(&mut self) -> Option<B> {
+        loop {
+            let item = self.iter.next()?;
+            if let Some(value) = (self.f)(item) {
+                return Some(value);
+            }
+        }
+    }
+}
+
+/// Iterator returned by [`LendingIterator::map_while`].
+#[derive(Clone, Debug)]
+pub struct MapWhile<I, F> {
+    iter: I,
+    f: F,
+    done: bool,
+}
+
+impl<I, B, F> Iterator for MapWhile<I, F>
+where
+    I: LendingIterator,
+    F: FnMut(I::Item<'_>) -> Option<B>,
+{
+    type Item = B;
+
+    fn next(&mut self) -> Option<B> {
+        if self.done {
+            return None;
+        }
+        let value = (self.f)(self.iter.next()?);
+        self.done = value.is_none();
+        value
+    }
+}
+
+/// Read the lines of `reader` into a single reused buffer.
+///
+/// Lines are lent without their trailing `"\n"` or `"\r\n"`.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::*;
+/// use comptools::lending::{lines, LendingIterator};
+///
+/// let words: usize = sum![line.unwrap().split(' ').count(); for line in lines("a b\nc".as_bytes())];
+/// assert_eq!(words, 3);
+/// ```
+pub fn lines<R: BufRead>(reader: R) -> Lines<R> {
+    Lines {
+        reader,
+        buf: String::new(),
+    }
+}
+
+/// Lending iterator returned by [`lines`].
+#[derive(Debug)]
+pub struct Lines<R> {
+    reader: R,
+    buf: String,
+}
+
+impl<R: BufRead> Lines<R> {
+    /// Stop at the first I/O error, yielding the lines read until then.
+    pub fn stop_at_error(self) -> StopAtError<R> {
+        StopAtError {
+            lines: self,
+            failed: false,
+        }
+    }
+}
+
+impl<R: BufRead> LendingIterator for Lines<R> {
+    type Item<'a>
+        = io::Result<&'a str>
+    where
+        R: 'a;
+
+    fn next(&mut self) -> Option<Self::Item<'_>> {
+        self.buf.clear();
+        match self.reader.read_line(&mut self.buf) {
+            Ok(0) => None,
+            Ok(_) => {
+                let line = self.buf.strip_suffix('\n').unwrap_or(&self.buf);
+                Some(Ok(line.strip_suffix('\r').unwrap_or(line)))
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// Lending iterator returned by [`Lines::stop_at_error`].
+#[derive(Debug)]
+pub struct StopAtError<R> {
+    lines: Lines<R>,
+    failed: bool,
+}
+
+impl<R: BufRead> LendingIterator for StopAtError<R> {
+    type Item<'a>
+        = &'a str
+    where
+        R: 'a;
+
+    fn next(&mut self) -> Option<Self::Item<'_>> {
+        if self.failed {
+            return None;
+        }
+        match self.lines.next()? {
+            Ok(line) => Some(line),
+            Err(_) => {
+                self.failed = true;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let text = "a\r\nbb\n\nccc";
+        let expected: Vec<String> = text.lines().map(str::to_owned).collect();
+        let got: Vec<String> =
+            crate::vect![line.unwrap().to_owned(); for line in lines(text.as_bytes())];
+        assert_eq!(expected, got);
+        let expected: Vec<usize> = text
+            .lines()
+            .take_while(|l| !l.is_empty())
+            .map(str::len)
+            .collect();
+        let got: Vec<usize> = crate::vect![line.len(); for line in lines(text.as_bytes()).stop_at_error(); while !line.is_empty()];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_lines_stop_at_error() {
+        // Fails once, after the first chunk, then reads again
+        struct Flaky(Vec<io::Result<&'static [u8]>>);
+        impl io::Read for Flaky {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.pop() {
+                    Some(chunk) => io::Read::read(&mut chunk?, buf),
+                    None => Ok(0),
+                }
+            }
+        }
+        let reader = Flaky(vec![
+            Ok(b"c\n"),
+            Err(io::ErrorKind::Other.into()),
+            Ok(b"a\nb\n"),
+        ]);
+        let got: Vec<String> = crate::vect![line.to_owned(); for line in lines(io::BufReader::new(reader)).stop_at_error()];
+        assert_eq!(vec!["a", "b"], got);
+    }
+}
@@ -16,6 +16,41 @@
 //! let sum: u64 = sum![x*x; for x in 1..; while x*x*x < 1234];
 //! assert_eq!(sum, 385);
 //! ```
+//!
+//! Helper iterators that are convenient as comprehension sources live in the
+//! [`sources`] module, and are re-exported at the root of the crate. Sources lending
+//! items that borrow from themselves live in the [`lending`] module.
+//!
+//! Comprehensions return a [`Comprehension`], a named wrapper around the adaptors they
+//! expand to.
+//!
+//! # Features
+//!
+//! - `std` (default): enables the macros and sources that need the standard library, e.g.,
+//!   to collect into a `HashMap`, or to spawn threads. Without it, the crate is `no_std`,
+//!   and [`iter`](macro@iter) and the macros reducing a comprehension to a single value, e.g.,
+//!   [`sum`](macro@sum), only depend on `core`. The `except key` and `unique` clauses need `std`.
+//! - `alloc`: enables the macros that need an allocator, e.g., [`sorted`](macro@sorted),
+//!   for `no_std` crates. [`vect`](macro@vect) works without it, as it only calls `collect`.
+//! - `allocator_api` (nightly only): enables [`vect_in`](macro@vect_in), which collects
+//!   into a `Vec<T, A>` using a user-provided allocator.
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[doc(hidden)]
+pub mod __private;
+mod comprehension;
+#[cfg(feature = "std")]
+pub mod lending;
+pub mod sources;
+#[cfg(feature = "std")]
+pub mod stats;
+
+pub use comprehension::Comprehension;
+pub use sources::*;
 
 /// Create an iterator using Python's list-comprehension style.
 ///
@@ -32,21 +67,59 @@
 /// # Variants
 ///
 /// Below are all the possible variants you can use with the [`iter`](macro@iter) macro.
+/// Like the expression, conditions are written against the values bound by the `for` clause.
 ///
 /// ## Filter by value
 ///
-/// **Warning:** filter condition uses references
-///
 /// ```rust
 /// # #[macro_use] extern crate comptools;
 /// // iter![f(x); for x in iter; if cond(x)];
-/// let iter = iter![x*x; for x in 1..10; if x < &5];
+/// let iter = iter![x*x; for x in 1..10; if x < 5];
 /// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 4, 9, 16]);
 /// // or:
 /// // iter![f(x); if cond(x); for x in iter];
-/// let iter = iter![x*x; if x < &5; for x in 1..10];
+/// let iter = iter![x*x; if x < 5; for x in 1..10];
 /// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 4, 9, 16]);
 /// // Same as filter_map
+/// // Any number of conditions can be chained, items are kept if all of them hold
+/// let iter = iter![x*x; for x in 1..10; if x < 5; if x % 2 == 0];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![4, 16]);
+/// ```
+///
+/// ## Filter by pattern
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(y); for x in iter; if let pat(y) = g(x)];
+/// let iter = iter![n * 2; for s in ["1", "a", "3"].into_iter(); if let Ok(n) = s.parse::<u64>()];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![2, 6]);
+/// // Works with other clauses
+/// let iter = iter![n; for s in ["1", "a", "3"].into_iter(); if let Ok(n) = s.parse::<u64>(); if n > 1];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![3]);
+/// ```
+///
+/// ## Filter by variant
+///
+/// An `if matches` clause tests the items against a pattern without binding anything, like
+/// with [`matches`]. The clause must directly follow the `for` clause whose items it tests.
+/// Items are matched by reference, so variables bound for a guard are references.
+///
+/// As `if matches(x)` calls a function named `matches`, a pattern starting with a parenthesis,
+/// like a tuple pattern, is written with a leading `|`, e.g., `if matches | (_, None)`.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// enum Event {
+///     Click { x: u32 },
+///     Key(char),
+/// }
+/// let events = [Event::Key('a'), Event::Click { x: 1 }, Event::Key('b'), Event::Click { x: 5 }];
+/// // iter![f(x); for x in iter; if matches pat];
+/// let iter = iter![i; for (i, e) in events.iter().enumerate(); if matches | (_, Event::Click { .. })];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 3]);
+/// // Guards and other clauses can follow
+/// let count = count![for e in &events; if matches Event::Key(c) if c.is_ascii_lowercase(); if true];
+/// assert_eq!(count, 2);
 /// ```
 ///
 /// ## Conditional mapping
@@ -63,148 +136,4158 @@
 ///
 /// ## Map while
 ///
-/// **Warning:** while condition uses references
-///
 /// ```rust
 /// # #[macro_use] extern crate comptools;
 /// // iter![f(x); for x in iter; while cond(x)];
-/// let iter = iter![x*x; for x in 1..10; while x < &5];
+/// let iter = iter![x*x; for x in 1..10; while x < 5];
 /// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 4, 9, 16]);
 /// // or
 /// // iter![f(x); while cond(x); for x in iter];
-/// let iter = iter![x*x; while x < &5; for x in 1..10];
+/// let iter = iter![x*x; while x < 5; for x in 1..10];
 /// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 4, 9, 16]);
 /// ```
-#[macro_export]
-macro_rules! iter {
-    // [f(x); for x in iter]
-    ($exp:expr; for $item:ident in $iter:expr) => {
-        $iter.map(|$item| $exp)
-    };
-    // [f(x); for x in iter; if cond(x)]
-    ($exp:expr; for $item:ident in $iter:expr; if $ifexp:expr) => {
-        $iter.filter(|$item| $ifexp).map(|$item| $exp)
-    };
-    // [f(x); for x in iter; if cond(x); else g(x)]
-    ($exp:expr; for $item:ident in $iter:expr; if $ifexp:expr; else $elsexp:expr) => {
-        $iter.map(|$item| if $ifexp {$exp} else {$elsexp})
-    };
-    // [f(x); for x in iter; while cond(x)]
-    ($exp:expr; for $item:ident in $iter:expr; while $whilexp:expr) => {
-        $iter.take_while(|$item| $whilexp).map(|$item| $exp)
-    };
-    // Below are alternative ways for calling this macro
-    //
-    ($exp:expr; if $ifexp:expr; for $item:ident in $iter:expr) => {
-        iter![$exp; for $item in $iter; if $ifexp]
-    };
-    ($exp:expr; if $ifexp:expr; else $elsexp:expr; for $item:ident in $iter:expr) => {
-        iter![$exp; for $item in $iter; if $ifexp; else $elsexp]
-    };
-    ($exp:expr; while $whilexp:expr; for $item:ident in $iter:expr) => {
-        iter![$exp; for $item in $iter; while $whilexp]
-    };
-}
-
-/// Return sum of values of an iterator using Python's list-comprehension style.
 ///
-/// # Basic usage
+/// ## Combining clauses
+///
+/// `if` and `while` clauses can be combined in any order, and are evaluated in that order:
+/// an item failing an `if` is skipped, and the first item reaching a failing `while` stops the iteration.
 ///
 /// ```rust
 /// # #[macro_use] extern crate comptools;
-/// // sum![f(x); for x in iter];
-/// // Create an iterator and sum its values
-/// let sum: u64 = sum![x*x; for x in 1..10];
-/// assert_eq!(sum, 285);
-/// // Same as iter![...].sum()
+/// // iter![f(x); for x in iter; while cond(x); if cond(x)];
+/// let iter = iter![x; for x in 1..; while x < 10; if x % 3 == 0];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![3, 6, 9]);
+/// // Same as take_while, then filter. Conversely, filter, then take_while:
+/// let iter = iter![x; for x in [3, 8, 6, 9].into_iter(); if x % 3 == 0; while x < 8];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![3, 6]);
 /// ```
 ///
-/// For more details, refer to the documentation of [`iter`](macro@iter).
-#[macro_export]
-macro_rules! sum {
-    ($($body:tt)*) => {{
-    (iter![$($body)*]).sum()
-    }};
-}
-
-/// Return product of values of an iterator using Python's list-comprehension style.
+/// ## Intermediate bindings
 ///
-/// # Basic usage
+/// A `let` clause binds the value of an expression, computed once per item, for use in
+/// the clauses that follow it and in the mapped expression.
 ///
 /// ```rust
 /// # #[macro_use] extern crate comptools;
-/// // product![f(x); for x in iter];
-/// // Create an iterator and multiply its values
-/// let product: u64 = product![x*x; for x in 1..10];
-/// assert_eq!(product, 131681894400);
-/// // Same as iter![...].product()
+/// // iter![f(y); for x in iter; let y = g(x); if cond(y)];
+/// let iter = iter![(w, n); for w in "a bb ccc".split(' '); let n = w.len(); if n > 1];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![("bb", 2), ("ccc", 3)]);
+/// // Any irrefutable pattern is accepted
+/// let iter = iter![a * b; for x in 1..4; let (a, b) = (x, x + 1)];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![2, 6, 12]);
 /// ```
 ///
-/// For more details, refer to the documentation of [`iter`](macro@iter).
-#[macro_export]
-macro_rules! product {
-    ($($body:tt)*) => {{
-    (iter![$($body)*]).product()
-    }};
-}
-
-/// Create a collection using Python's list-comprehension style.
+/// ## Nested loops
 ///
-/// # Basic usage
+/// Any number of `for` clauses can be chained, each followed by its own `if` and `while`
+/// clauses, like nested loops. Inner loops are flattened, and `while` only stops the loop
+/// it follows.
+///
+/// Inner loops can use the variables of outer loops, even in their source. Each of them
+/// gets its own clone of the items of outer loops, which must therefore implement [`Clone`].
+///
+/// **Warning:** inner loops take ownership of the variables they use, see
+/// [moving captured variables](#moving-captured-variables). Non-[`Copy`] values from
+/// the environment should be used through references, e.g., with `let v = &v;`.
 ///
 /// ```rust
 /// # #[macro_use] extern crate comptools;
-/// // vect![f(x); for x in iter];
-/// // Create a collection
-/// let vec: Vec<u64> = vect![x*x; for x in 1..10];
-/// assert_eq!(vec, vec![1, 4, 9, 16, 25, 36, 49, 64, 81]);
-/// // Same as iter![...].collect()
+/// // iter![f(x, y); for x in iter; for y in iter];
+/// let iter = iter![x * y; for x in 1..4; for y in 1..3];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 2, 2, 4, 3, 6]);
+/// // Clauses apply to the loop they follow
+/// let iter = iter![(x, y); for x in 1..; while x < 4; for y in "ab".chars(); if x % 2 == 1];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![(1, 'a'), (1, 'b'), (3, 'a'), (3, 'b')]);
+/// // Inner sources can depend on outer variables
+/// let iter = iter![(i, j); for i in 0..4; for j in 0..i];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![(1, 0), (2, 0), (2, 1), (3, 0), (3, 1), (3, 2)]);
+/// let words = ["ab".to_owned(), "c".to_owned()];
+/// let iter = iter![(w, c); for w in words.into_iter(); for c in w.clone().into_bytes().into_iter()];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![("ab".to_owned(), b'a'), ("ab".to_owned(), b'b'), ("c".to_owned(), b'c')]);
 /// ```
 ///
-/// For more details, refer to the documentation of [`iter`](macro@iter).
+/// ## Flattening
+///
+/// A final `flatten` clause flattens the values, which must implement [`IntoIterator`],
+/// like Python's `yield from`. This is the same as `flat_map` instead of `map`.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(x); for x in iter; flatten];
+/// let iter = iter![w.chars(); for w in ["ab", "", "c"].into_iter(); flatten];
+/// assert_eq!(iter.collect::<String>(), "abc");
+/// // Can follow any clause
+/// let iter = iter![0..x; for x in 1..; while x < 4; flatten];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![0, 0, 1, 0, 1, 2]);
+/// ```
+///
+/// ## Unique values
+///
+/// A final `unique` clause drops the values already produced by the comprehension, keeping
+/// the first occurrence of each, like Python's `set(...)` but in order. Values must
+/// implement [`Hash`](std::hash::Hash), [`Eq`] and [`Clone`], and are kept in a `HashSet`.
+///
+#[cfg_attr(feature = "std", doc = "```rust")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(x); for x in iter; unique];
+/// let iter = iter![x % 3; for x in [5, 3, 2, 4, 6].into_iter(); unique];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![2, 0, 1]);
+/// // Applies to all loops
+/// let iter = iter![x + y; for x in 0..3; for y in 0..3; unique];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+/// ```
+///
+/// ## Reversing
+///
+/// A final `rev` clause reverses the order of the values, like Python's `reversed(...)`.
+/// The sources must implement [`DoubleEndedIterator`], and `while` clauses cannot be used,
+/// as they depend on the order of iteration. For the same reason, `unique` must come after `rev`.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(x); for x in iter; rev];
+/// let iter = iter![x * x; for x in 1..6; if x % 2 == 1; rev];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![25, 9, 1]);
+/// // Applies to all loops
+/// let iter = iter![(x, y); for x in 0..2; for y in "ab".chars(); rev];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![(1, 'b'), (1, 'a'), (0, 'b'), (0, 'a')]);
+/// ```
+///
+/// ## Slicing
+///
+/// Final `step`, `skip`, and `take` clauses keep every n-th value, drop the first n values,
+/// and keep the first n values, respectively. They apply in the order they are written,
+/// e.g., `seq[1::2][:10]` in Python is `skip 1; step 2; take 10`.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(x); for x in iter; skip n; step n; take n];
+/// let iter = iter![x * 10; for x in 0..; skip 1; step 2; take 3];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![10, 30, 50]);
+/// // Can follow any clause
+/// let iter = iter![x; for x in 0..10; if x % 3 != 0; rev; take 4];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![8, 7, 5, 4]);
+/// ```
+///
+/// ## Exclude by key
+///
+/// **Warning:** key expression uses references
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// let items = [(1, "a"), (2, "b"), (3, "c"), (4, "d")];
+/// let blocked = vec![2, 4];
+/// // iter![f(x); for x in iter; except key k(x) in keys];
+/// // Keys are collected in a HashSet before iterating
+/// let iter = iter![x.1; for x in items.iter(); except key x.0 in &blocked];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec!["a", "c"]);
+/// // Can be followed by any variant
+/// let iter = iter![x.1; for x in items.iter(); except key x.0 in &blocked; if x.0 > 1];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec!["c"]);
+/// ```
+///
+/// ## Mutable state
+///
+/// **Warning:** the state is a mutable reference
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(x, s); for x in iter; state mut s = init];
+/// // Running sum
+/// let iter = iter![{ *total += x; *total }; for x in 1..6; state mut total = 0];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 3, 6, 10, 15]);
+/// // iter![f(x, s); for x in iter; state mut s = init; if cond(x, s)];
+/// // Drop consecutive duplicates
+/// let iter = iter![x; for x in [1, 1, 2, 2, 1].into_iter(); state mut last = None; if last.replace(x) != Some(x)];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 2, 1]);
+/// ```
+///
+/// The state is bound where its clause appears, so only the items passing the previous
+/// clauses reach it, and it can be combined with any other clause. Each `for` clause can
+/// have one state, which restarts for each item of outer levels. As the state depends on
+/// the order of the items, such comprehensions cannot be reversed.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // Number the words that are not empty
+/// let iter = iter![format!("{i}. {w}"); for w in ["a", "", "b"]; if !w.is_empty(); state mut n = 0; let i = { *n += 1; *n }; take 2];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec!["1. a", "2. b"]);
+/// ```
+///
+/// ## Running state
+///
+/// With `scan`, the state is instead a value, updated from its previous value and the item
+/// before evaluating the expression, like Python's `itertools.accumulate`. The state is cloned
+/// for each item.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(x, s); for x in iter; scan s = init => g(s, x)];
+/// // Running sum
+/// let iter = iter![total; for x in 1..6; scan total = 0 => total + x];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 3, 6, 10, 15]);
+/// // iter![f(x, s); for x in iter; scan s = init => g(s, x); if cond(x, s)];
+/// // Running maximum, when it changes
+/// let iter = iter![high; for x in [3, 1, 4, 1, 5].into_iter(); scan high = 0 => high.max(x); if high == x];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![3, 4, 5]);
+/// // Works in inner loops, restarting for each outer item
+/// let iter = iter![s; for x in 1..4; for y in 0..x; scan s = 0 => s + y; take 4];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![0, 0, 1, 0]);
+/// ```
+///
+/// # Sources
+///
+/// Like in Python's `for x in seq`, the source of a `for` clause can be anything implementing
+/// [`IntoIterator`], not only iterators. Collections are consumed, while references to them
+/// yield references, so that they can be used again afterwards.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// let words = vec!["a", "bb", "ccc"];
+/// // iter![f(x); for x in &collection];
+/// let lengths: Vec<usize> = vect![w.len(); for w in &words; if w.len() > 1];
+/// assert_eq!(lengths, vec![2, 3]);
+/// // iter![f(x); for x in collection];
+/// let upper: Vec<String> = vect![w.to_uppercase(); for w in words];
+/// assert_eq!(upper, vec!["A", "BB", "CCC"]);
+/// let sum: u64 = sum![x * y; for x in [1, 2]; for y in [10, 20]];
+/// assert_eq!(sum, 90);
+/// ```
+///
+/// # Scoping
+///
+/// Like in Python 3, the variables bound by `for` are local to the comprehension: they only
+/// shadow variables of the same name inside the expressions of the clauses, and are left
+/// untouched outside. The source iterator is evaluated in the enclosing scope, so it still
+/// refers to the outer variables. Macros refer to each other by their full path, so only the
+/// macro being called needs to be imported.
+///
+/// ```rust
+/// use comptools::sum;
+///
+/// let x = 4;
+/// // The x in the source is the outer one
+/// let total: u64 = sum![x*x; for x in 1..=x];
+/// assert_eq!(total, 30);
+/// assert_eq!(x, 4);
+/// ```
+///
+/// ## Type annotation
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(x); for x: T in iter];
+/// // The type of the items can be pinned when it cannot be inferred
+/// let iter = iter![x.pow(2); for x: u64 in "1 2 3".split(' ').map(|s| s.parse().unwrap())];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 4, 9]);
+/// // Works with all variants
+/// let iter = iter![x.len(); for x: Vec<u8> in [vec![1], vec![2, 3]].into_iter(); if x[0] > 1];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![2]);
+/// ```
+///
+/// ## Patterns
+///
+/// Like in `for` loops, items can be destructured with any irrefutable pattern.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::collections::BTreeMap;
+///
+/// let map = BTreeMap::from([("a", 1), ("bb", 2), ("ccc", 3)]);
+/// // iter![f(x, y); for (x, y) in iter];
+/// let iter = iter![k.len() + v; for (k, v) in map.into_iter(); if v > 1];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![4, 6]);
+/// // Works with all variants
+/// let iter = iter![x + y; for &x in [1, 2].iter(); for (_, y) in [('a', 10), ('b', 20)].into_iter()];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![11, 21, 12, 22]);
+/// ```
+///
+/// ## Tuple unpacking
+///
+/// Parentheses around tuple patterns can be omitted.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::collections::BTreeMap;
+///
+/// let map = BTreeMap::from([("a", 1), ("bb", 2), ("ccc", 3)]);
+/// // iter![f(x, y); for x, y in iter];
+/// let iter = iter![k.len() * v; for k, v in map.iter(); if v > &1];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![4, 9]);
+/// // Any number of comma-separated variables is accepted, and works with all variants
+/// let iter = iter![x + y + z; for x, y, z in [(1, 2, 3), (4, 5, 6)].into_iter()];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![6, 15]);
+/// ```
+///
+/// ## Zip
+///
+/// Several comma-separated sources are iterated in lockstep, like Python's `zip`,
+/// with one variable per source. The iteration stops with the shortest source.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(x, y); for x, y in iter_x, iter_y];
+/// let iter = iter![x + y; for x, y in [1, 2, 3], [10, 20]];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![11, 22]);
+/// // Any number of sources is accepted, and works with all variants
+/// let iter = iter![format!("{a}{b}{c}"); for a, b, c in 0..4, "xyz".chars(), [true; 3]; if a != 1];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec!["0xtrue", "2ztrue"]);
+/// ```
+///
+/// A source prefixed with `scalar` is a single value, repeated for each item of the other
+/// sources, like broadcasting in NumPy. The value is evaluated once, and must be [`Clone`].
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(x, k); for x, k in iter, scalar k];
+/// let iter = iter![x * k; for x, k in [1, 2, 3], scalar 10];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![10, 20, 30]);
+/// ```
+///
+/// ## Enumerate
+///
+/// Prefixing the source with `enumerate` pairs each item with its index, starting from zero,
+/// like Python's `enumerate`. The source can be any [`IntoIterator`].
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(i, x); for i, x in enumerate iter];
+/// let iter = iter![i * x; for i, x in enumerate [5, 6, 7]; if i > 0];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![6, 14]);
+/// // Works with any pattern, and in inner loops
+/// let iter = iter![(c, j); for (_, c) in enumerate "ab".chars(); for (j, _) in enumerate 0..2];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![('a', 0), ('a', 1), ('b', 0), ('b', 1)]);
+/// ```
+///
+/// A trailing `from start` sets the first index, like `enumerate(iter, start=1)` in Python.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(i, x); for i, x in enumerate iter from start];
+/// let iter = iter![format!("{i}. {x}"); for i, x in enumerate ["foo", "bar"] from 1];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec!["1. foo", "2. bar"]);
+/// ```
+///
+/// ## Chain
+///
+/// Prefixing comma-separated sources with `chain` iterates them one after the other, like
+/// Python's `itertools.chain`. The sources can be any [`IntoIterator`] of the same items.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // iter![f(x); for x in chain iter_a, iter_b];
+/// let extra = vec![10, 20];
+/// let iter = iter![x * 2; for x in chain 1..3, [5], extra; if x != 5];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![2, 4, 20, 40]);
+/// // Works with any pattern, and in inner loops
+/// let iter = iter![(a, b); for a, b in chain [(1, 'x')], [(2, 'y')]; for _ in chain [()], [()]];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![(1, 'x'), (1, 'x'), (2, 'y'), (2, 'y')]);
+/// ```
+///
+/// ## Scoped resources
+///
+/// A resource can be opened before iterating with a leading `with` clause, and used by the source.
+/// Like a context manager, the resource is moved into the iterator and dropped with it,
+/// so the source must take ownership of it, e.g., [`BufRead::lines`](std::io::BufRead::lines).
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::io::{BufRead, BufReader, Cursor};
+///
+/// // iter![f(x); with r = init; for x in iter(r)];
+/// let iter = iter![line.unwrap().len(); with reader = BufReader::new(Cursor::new("a\nbb")); for line in reader.lines()];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![1, 2]);
+/// ```
+///
+/// Errors can be propagated with `?` from the initializer, e.g.,
+/// `vect![line?; with file = File::open(path)?; for line in BufReader::new(file).lines()]`.
+///
+/// ## Propagating errors
+///
+/// Prefixed with `try`, the expression can use `?`, like in a closure returning a `Result`:
+/// each value is wrapped in `Ok`, and errors become the values of the iterator.
+/// The error type is usually inferred from the one of the collection, e.g., with
+/// [`try_vect`](macro@try_vect), and clauses cannot use `?`.
+///
+#[cfg_attr(feature = "alloc", doc = "```rust")]
+#[cfg_attr(not(feature = "alloc"), doc = "```ignore")]
+/// # #[macro_use] extern crate comptools;
+/// use std::num::ParseIntError;
+///
+/// fn parse_pairs(pairs: &[(&str, &str)]) -> Result<Vec<u32>, ParseIntError> {
+///     // iter![try f(x)?; for x in iter];
+///     try_vect![try a.parse::<u32>()? + b.parse::<u32>()?; for (a, b) in pairs.iter()]
+/// }
+/// assert_eq!(parse_pairs(&[("1", "2"), ("3", "4")]), Ok(vec![3, 7]));
+/// assert!(parse_pairs(&[("1", "2"), ("x", "4")]).is_err());
+/// ```
+///
+/// ## Moving captured variables
+///
+/// Like closures, the comprehension can be prefixed with `move`, so that it takes
+/// ownership of the variables it uses, e.g., to return it from a function.
+/// Each closure gets its own copy, so variables used in several places must be [`Copy`].
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// fn multiples(n: u64) -> impl Iterator<Item = u64> {
+///     // iter![move f(x); for x in iter];
+///     iter![move x * n; for x in 1..]
+/// }
+/// assert_eq!(multiples(3).take(3).collect::<Vec<_>>(), vec![3, 6, 9]);
+/// ```
+///
+/// See also [`def_iter`](macro@def_iter) to declare such functions directly.
 #[macro_export]
-macro_rules! vect {
-    ($($body:tt)*) => {{
-    (iter![$($body)*]).collect()
+macro_rules! iter {
+    // [f(i, x); for i, x in enumerate iter]
+    (@[$($mv:tt)?] $exp:expr; for $index:ident, $item:pat in enumerate $($rest:tt)+) => {
+        $crate::iter!(@[$($mv)?] $exp; for ($index, $item) in enumerate $($rest)+)
+    };
+    // [f(i, x); for (i, x) in enumerate iter from start]
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in enumerate $($rest:tt)+) => {
+        $crate::iter!(@from [@[$($mv)?] $exp; for $item in] [] $($rest)+)
+    };
+    // The source ends before `from`, or before the first clause. Paths and fields named `from`
+    // are part of the source.
+    (@from $head:tt [$($src:tt)*] :: from $($rest:tt)*) => {
+        $crate::iter!(@from $head [$($src)* :: from] $($rest)*)
+    };
+    (@from $head:tt [$($src:tt)*] . from $($rest:tt)*) => {
+        $crate::iter!(@from $head [$($src)* . from] $($rest)*)
+    };
+    (@from [$($head:tt)*] [$($src:tt)+] from $start:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!($($head)* $crate::iter!(@enumerate $($src)+, $start) $(; $($rest)*)?)
+    };
+    (@from [$($head:tt)*] [$($src:tt)+] $(; $($rest:tt)*)?) => {
+        $crate::iter!($($head)* $crate::iter!(@enumerate $($src)+) $(; $($rest)*)?)
+    };
+    (@from $head:tt [$($src:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::iter!(@from $head [$($src)* $next] $($rest)*)
+    };
+    (@enumerate $iter:expr) => {
+        ::core::iter::Iterator::enumerate(::core::iter::IntoIterator::into_iter($iter))
+    };
+    (@enumerate $iter:expr, $start:expr) => {
+        ::core::iter::Iterator::map($crate::iter!(@enumerate $iter), {
+            let start: usize = $start;
+            move |(i, item)| (start + i, item)
+        })
+    };
+    // [f(x, y); for x, y in chain iter_a, iter_b]
+    (@[$($mv:tt)?] $exp:expr; for $first:ident $(, $rest:ident)+ in chain $($tail:tt)+) => {
+        $crate::iter!(@[$($mv)?] $exp; for ($first $(, $rest)+) in chain $($tail)+)
+    };
+    // [f(x); for x in chain iter_a, iter_b]
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in chain $iter:expr $(, $iters:expr)+ $(; $($rest:tt)*)?) => {
+        $crate::iter!(@[$($mv)?] $exp; for $item in $crate::iter!(@chain $iter $(, $iters)+) $(; $($rest)*)?)
+    };
+    (@chain $iter:expr $(, $iters:expr)+) => {
+        ::core::iter::IntoIterator::into_iter($iter) $(.chain($iters))+
+    };
+    // [f(x); for x in iter]
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in $iter:expr) => {
+        $crate::__private::Source::into_source($iter).map($($mv)? |$item| $exp)
+    };
+    // [f(y); for x in iter; if let pat(y) = g(x)]
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in $iter:expr; if let $lpat:pat = $lexp:expr) => {
+        $crate::__private::Source::into_source($iter).filter_map($($mv)? |$item| if let $lpat = $lexp { Some($exp) } else { None })
+    };
+    // [f(x); for x in iter; if let pat(y) = g(x); ...]
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in $iter:expr; if let $($rest:tt)+) => {
+        $crate::iter!(@final [[$($mv)?] [] [$exp] [$item] [$iter] []] [] ; if let $($rest)+)
+    };
+    // [f(x); for x in iter; if matches(x); ...] calls a function named `matches`
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in $iter:expr; if matches ($($args:tt)*) $($rest:tt)*) => {
+        $crate::iter!(@[$($mv)?] $exp; for $item in $iter; if (matches)($($args)*) $($rest)*)
+    };
+    // [f(x); for x in iter; if matches pat; ...]
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in $iter:expr; if matches $($rest:tt)+) => {
+        $crate::iter!(@final [[$($mv)?] [] [$exp] [$item] [$iter] []] [] ; if matches $($rest)+)
+    };
+    // [f(x); for x in iter; if cond(x)]
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in $iter:expr; if $ifexp:expr) => {
+        $crate::__private::Source::into_source($iter).filter_map($($mv)? |$item| if $ifexp { Some($exp) } else { None })
+    };
+    // [f(x); for x in iter; if cond(x); else g(x)]
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in $iter:expr; if $ifexp:expr; else $elsexp:expr) => {
+        $crate::__private::Source::into_source($iter).map($($mv)? |$item| if $ifexp {$exp} else {$elsexp})
+    };
+    // [f(x); for x in iter; while cond(x)]
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in $iter:expr; while $whilexp:expr) => {
+        $crate::__private::Source::into_source($iter).map_while($($mv)? |$item| if $whilexp { Some($exp) } else { None })
+    };
+    // [f(x); for x in iter; except key k(x) in keys; ...]
+    (@[$($mv:tt)?] $exp:expr; for $item:pat in $iter:expr; except key $($rest:tt)+) => {
+        $crate::iter!(@[$($mv)?] @except [$exp; $item; $iter] [] $($rest)+)
+    };
+    (@[$($mv:tt)?] @except [$exp:expr; $item:tt; $iter:expr] [$($key:tt)+] in $keys:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@[$($mv)?] $exp; for $item in {
+            let excluded: ::std::collections::HashSet<_> = ::core::iter::IntoIterator::into_iter($keys).collect();
+            $crate::__private::Source::into_source($iter).filter(move |$item| !excluded.contains(&$($key)+))
+        } $(; $($rest)*)?)
+    };
+    (@[$($mv:tt)?] @except [$($head:tt)*] [$($key:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::iter!(@[$($mv)?] @except [$($head)*] [$($key)* $next] $($rest)*)
+    };
+    // [f(x, y); for x, y in iter_x, iter_y]
+    (@[$($mv:tt)?] $exp:expr; for $first:ident $(, $rest:ident)+ in $iter:expr $(, $iters:expr)+ $(; $($clauses:tt)*)?) => {
+        $crate::iter!(@zip [$($mv)?] [$exp] [$(; $($clauses)*)?] [$first] [::core::iter::IntoIterator::into_iter($iter)] $([$rest $iters])+)
+    };
+    (@zip $mv:tt $exp:tt $clauses:tt [$pat:pat] [$iter:expr] [$var:ident $src:expr] $($rest:tt)*) => {
+        $crate::iter!(@zip $mv $exp $clauses [($pat, $var)] [::core::iter::Iterator::zip($iter, $src)] $($rest)*)
+    };
+    (@zip [$($mv:tt)?] [$exp:expr] [$($clauses:tt)*] [$pat:pat] [$iter:expr]) => {
+        $crate::iter!(@[$($mv)?] $exp; for $pat in $iter $($clauses)*)
+    };
+    // [f(x, y); for x, y in iter]
+    (@[$($mv:tt)?] $exp:expr; for $first:ident $(, $rest:ident)+ in $iter:expr) => {
+        $crate::__private::Source::into_source($iter).map($($mv)? |($first $(, $rest)+)| {
+            let _ = (&$first $(, &$rest)+); // Not all variables need to be used
+            $exp
+        })
+    };
+    // [f(x, y); for x, y in iter; if let pat(z) = g(x, y); ...]
+    (@[$($mv:tt)?] $exp:expr; for $first:ident $(, $rest:ident)+ in $iter:expr; if let $($clauses:tt)+) => {
+        $crate::iter!(@[$($mv)?] $exp; for ($first $(, $rest)+) in $iter; if let $($clauses)+)
+    };
+    // [f(x, y); for x, y in iter; if matches pat; ...]
+    (@[$($mv:tt)?] $exp:expr; for $first:ident $(, $rest:ident)+ in $iter:expr; if matches $($clauses:tt)+) => {
+        $crate::iter!(@[$($mv)?] $exp; for ($first $(, $rest)+) in $iter; if matches $($clauses)+)
+    };
+    // [f(x, y); for x, y in iter; if cond(x, y)]
+    (@[$($mv:tt)?] $exp:expr; for $first:ident $(, $rest:ident)+ in $iter:expr; if $ifexp:expr) => {
+        $crate::__private::Source::into_source($iter).filter_map($($mv)? |($first $(, $rest)+)| {
+            let _ = (&$first $(, &$rest)+);
+            if $ifexp { Some($exp) } else { None }
+        })
+    };
+    // [f(x, y); for x, y in iter; if cond(x, y); else g(x, y)]
+    (@[$($mv:tt)?] $exp:expr; for $first:ident $(, $rest:ident)+ in $iter:expr; if $ifexp:expr; else $elsexp:expr) => {
+        $crate::iter!(@[$($mv)?] if $ifexp {$exp} else {$elsexp}; for $first $(, $rest)+ in $iter)
+    };
+    // [f(x, y); for x, y in iter; while cond(x, y)]
+    (@[$($mv:tt)?] $exp:expr; for $first:ident $(, $rest:ident)+ in $iter:expr; while $whilexp:expr) => {
+        $crate::__private::Source::into_source($iter).map_while($($mv)? |($first $(, $rest)+)| {
+            let _ = (&$first $(, &$rest)+);
+            if $whilexp { Some($exp) } else { None }
+        })
+    };
+    // [f(x, y); for x, y in iter; ...]
+    (@[$($mv:tt)?] $exp:expr; for $first:ident $(, $rest:ident)+ in $iter:expr; $($clauses:tt)+) => {
+        $crate::iter!(@[$($mv)?] $exp; for ($first $(, $rest)+) in $iter; $($clauses)+)
+    };
+    // [f(x); for x: T in iter; ...]
+    (@[$($mv:tt)?] $exp:expr; for $item:ident : $($rest:tt)+) => {
+        $crate::iter!(@[$($mv)?] @typed [$exp; $item] [] $($rest)+)
+    };
+    (@[$($mv:tt)?] @typed [$exp:expr; $item:ident] [$($ty:tt)+] in $iter:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@[$($mv)?] $exp; for $item in $crate::__private::typed::<$($ty)+, _>($crate::__private::Source::into_source($iter)) $(; $($rest)*)?)
+    };
+    (@[$($mv:tt)?] @typed [$exp:expr; $item:ident] [$($ty:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::iter!(@[$($mv)?] @typed [$exp; $item] [$($ty)* $next] $($rest)*)
+    };
+    // [f(x); with r = init; for x in iter(r); ...]
+    (@[$($mv:tt)?] $exp:expr; with $res:ident = $init:expr; $($rest:tt)+) => {{
+        let $res = $init;
+        $crate::iter!(@[$($mv)?] $exp; $($rest)+)
     }};
-}
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn test_vect() {
-        let expected: Vec<u64> = (1..10).map(|x| x * x).collect();
-        let got: Vec<u64> = vect![x*x; for x in 1..10];
-        assert_eq!(expected, got);
-    }
-    #[test]
-    fn test_vect_if() {
+    // [f(x, y); for x in iter; ...; for y in iter(x); ...]
+    //
+    // Each `for` clause is a level, whose `if` and `while` clauses are evaluated in order
+    // by a single closure, after binding the item. Inner levels are flattened, and take ownership of a clone of
+    // the items of outer levels, so that outer variables can be used freely.
+    (@[$($mv:tt)?] $exp:expr; for $pat:pat in $iter:expr; $($rest:tt)+) => {
+        $crate::iter!(@final [[$($mv)?] [] [$exp] [$pat] [$iter] []] [] ; $($rest)+)
+    };
+    // Final clauses apply to the whole comprehension, in order, so they are looked for first
+    (@final $head:tt [$($rest:tt)*] ; unique) => {
+        $crate::__private::unique($crate::iter!(@final $head [] $($rest)*))
+    };
+    (@final $head:tt [$($rest:tt)*] ; rev) => {
+        ::core::iter::Iterator::rev($crate::iter!(@final $head [] $($rest)*))
+    };
+    (@final $head:tt [$($rest:tt)*] ; step $step:expr) => {
+        ::core::iter::Iterator::step_by($crate::iter!(@final $head [] $($rest)*), $step)
+    };
+    (@final $head:tt [$($rest:tt)*] ; skip $skip:expr) => {
+        ::core::iter::Iterator::skip($crate::iter!(@final $head [] $($rest)*), $skip)
+    };
+    (@final $head:tt [$($rest:tt)*] ; take $take:expr) => {
+        ::core::iter::Iterator::take($crate::iter!(@final $head [] $($rest)*), $take)
+    };
+    (@final $head:tt [$($rest:tt)*] $next:tt $($tail:tt)*) => {
+        $crate::iter!(@final $head [$($rest)* $next] $($tail)*)
+    };
+    (@final [$($head:tt)*] [$(; $($rest:tt)*)?]) => {
+        $crate::iter!(@level $($head)* $($($rest)*)?)
+    };
+    (@for $mv:tt $outer:tt $exp:tt for $pat:pat in enumerate $($rest:tt)+) => {
+        $crate::iter!(@from [@for $mv $outer $exp for $pat in] [] $($rest)+)
+    };
+    (@for $mv:tt $outer:tt $exp:tt for $pat:pat in chain $iter:expr $(, $iters:expr)+ $(; $($rest:tt)*)?) => {
+        $crate::iter!(@level $mv $outer $exp [$pat] [$crate::iter!(@chain $iter $(, $iters)+)] [] $($($rest)*)?)
+    };
+    (@for $mv:tt $outer:tt $exp:tt for $pat:pat in $iter:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@level $mv $outer $exp [$pat] [$iter] [] $($($rest)*)?)
+    };
+    // [f(x); for x in iter; if matches(x)] calls a function named `matches`
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt $cls:tt if matches ($($args:tt)*) $($rest:tt)*) => {
+        $crate::iter!(@level $mv $outer $exp $pat $iter $cls if (matches)($($args)*) $($rest)*)
+    };
+    (@level $mv:tt $outer:tt [$exp:expr] $pat:tt $iter:tt [$($cl:tt)*] if $ifexp:expr; else $elsexp:expr) => {
+        $crate::iter!(@build $mv $outer [if $ifexp {$exp} else {$elsexp}] $pat $iter [$($cl)*])
+    };
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [$($cl:tt)*] if let $lpat:pat = $lexp:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@level $mv $outer $exp $pat $iter [$($cl)* [if let $lpat = $lexp]] $($($rest)*)?)
+    };
+    // [f(x); for x in iter; if matches pat] tests the items of the `for` clause
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [] if matches $($rest:tt)+) => {
+        $crate::iter!(@matches [$mv $outer $exp $pat $iter] [] $($rest)+)
+    };
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [$($cl:tt)+] if matches $($rest:tt)+) => {
+        ::core::compile_error!("`if matches` must directly follow a `for` clause")
+    };
+    // The pattern is matched against the items of the source, before binding them
+    (@matches [$mv:tt $outer:tt $exp:tt $pat:tt [$iter:expr]] [$($mpat:tt)+] $(; $($rest:tt)*)?) => {
+        $crate::iter!(@level $mv $outer $exp $pat [::core::iter::Iterator::filter(
+            $crate::__private::Source::into_source($iter),
+            |item| ::core::matches!(item, $($mpat)+),
+        )] [] $($($rest)*)?)
+    };
+    (@matches $head:tt [$($mpat:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::iter!(@matches $head [$($mpat)* $next] $($rest)*)
+    };
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [$($cl:tt)*] if $ifexp:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@level $mv $outer $exp $pat $iter [$($cl)* [if $ifexp]] $($($rest)*)?)
+    };
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [$($cl:tt)*] let $lpat:pat = $lexp:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@level $mv $outer $exp $pat $iter [$($cl)* [let $lpat = $lexp]] $($($rest)*)?)
+    };
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [$($cl:tt)*] while $whilexp:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@level $mv $outer $exp $pat $iter [$($cl)* [while $whilexp]] $($($rest)*)?)
+    };
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [$($cl:tt)*] state mut $state:ident = $init:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@level $mv $outer $exp $pat $iter [$($cl)* [state $state = $init]] $($($rest)*)?)
+    };
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [$($cl:tt)*] scan $state:ident = $init:expr => $update:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@level $mv $outer $exp $pat $iter [$($cl)* [scan $state = $init => $update]] $($($rest)*)?)
+    };
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [$($cl:tt)*] flatten) => {
+        $crate::iter!(@build $mv $outer $exp $pat $iter [$($cl)*]).flatten()
+    };
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [$($cl:tt)*] for $($rest:tt)+) => {
+        $crate::iter!(@build $mv $outer [@for $exp for $($rest)+] $pat $iter [$($cl)*])
+    };
+    (@level $mv:tt $outer:tt $exp:tt $pat:tt $iter:tt [$($cl:tt)*]) => {
+        $crate::iter!(@build $mv $outer $exp $pat $iter [$($cl)*])
+    };
+    // Inner level: the item is kept to be cloned by the next level, which is flattened
+    (@build [$($mv:tt)?] [$($outer:tt)*] [@for $exp:tt $($rest:tt)+] [$pat:pat] [$iter:expr] [$($cl:tt)*]) => {
+        $crate::iter!(@build [$($mv)?] [$($outer)*] [
+            $crate::iter!(@for [move] [$($outer)* [[$pat] item]] $exp $($rest)+)
+        ] [$pat] [$iter] [$($cl)*] [::core::clone::Clone::clone(&item)] item acc)
+        .flatten()
+    };
+    (@build [$($mv:tt)?] [$($outer:tt)*] [$exp:expr] [$pat:pat] [$iter:expr] [$($cl:tt)*]) => {
+        $crate::iter!(@build [$($mv)?] [$($outer)*] [$exp] [$pat] [$iter] [$($cl)*] [item] item acc)
+    };
+    (@build [$($mv:tt)?] [$([[$opat:pat] $ovar:ident])*] [$keep:expr] [$pat:pat] [$iter:expr] [] [$bind:expr] $item:ident $acc:ident) => {
+        $crate::__private::Source::into_source($iter).map($($mv)? |$item| {
+            $crate::iter!(@outer $([[$opat] $ovar])*);
+            #[allow(unused_variables)]
+            let $pat = $bind;
+            $keep
+        })
+    };
+    (@build [$($mv:tt)?] [$([[$opat:pat] $ovar:ident])*] [$keep:expr] [$pat:pat] [$iter:expr] [$($cl:tt)+] [$bind:expr] $item:ident $acc:ident) => {
+        $crate::iter!(@stateful [[$($mv)?] [$iter] $item $acc [{
+            $crate::iter!(@outer $([[$opat] $ovar])*);
+            #[allow(unused_variables)]
+            let $pat = $bind;
+            $crate::iter!(@clauses [$keep] $acc $($cl)+)
+        }]] [$($cl)+] $($cl)+)
+    };
+    // Levels with a `state` or `scan` clause carry the state from item to item, which may
+    // also stop the level
+    (@stateful $head:tt $cls:tt [state $state:ident = $init:expr] $($cl:tt)*) => {
+        $crate::iter!(@state $head [$init] $($cl)*)
+    };
+    (@stateful $head:tt $cls:tt [scan $state:ident = $init:expr => $update:expr] $($cl:tt)*) => {
+        $crate::iter!(@state $head [::core::option::Option::Some($init)] $($cl)*)
+    };
+    (@stateful $head:tt $cls:tt $next:tt $($cl:tt)*) => {
+        $crate::iter!(@stateful $head $cls $($cl)*)
+    };
+    (@stateful [$mv:tt $iter:tt $item:ident $acc:ident $body:tt] [$($cl:tt)*]) => {
+        $crate::iter!(@adaptor $mv $iter $item $body $($cl)*)
+    };
+    (@state $head:tt $init:tt [state $($x:tt)*] $($cl:tt)*) => {
+        ::core::compile_error!("only one `state` or `scan` clause is allowed per `for` clause")
+    };
+    (@state $head:tt $init:tt [scan $($x:tt)*] $($cl:tt)*) => {
+        ::core::compile_error!("only one `state` or `scan` clause is allowed per `for` clause")
+    };
+    (@state $head:tt $init:tt $next:tt $($cl:tt)*) => {
+        $crate::iter!(@state $head $init $($cl)*)
+    };
+    (@state [[$($mv:tt)?] [$iter:expr] $item:ident $acc:ident [$body:expr]] [$init:expr]) => {
+        $crate::__private::Source::into_source($iter).scan($init, $($mv)? |$acc, $item| $body).flatten()
+    };
+    // Levels with a `while` clause may stop, otherwise items are only filtered, which keeps
+    // the iterator double-ended
+    (@adaptor [$($mv:tt)?] [$iter:expr] $item:ident [$body:expr] [while $($w:tt)*] $($cl:tt)*) => {
+        $crate::__private::Source::into_source($iter).map_while($($mv)? |$item| $body).flatten()
+    };
+    (@adaptor $mv:tt $iter:tt $item:ident $body:tt $next:tt $($cl:tt)*) => {
+        $crate::iter!(@adaptor $mv $iter $item $body $($cl)*)
+    };
+    (@adaptor [$($mv:tt)?] [$iter:expr] $item:ident [$body:expr]) => {
+        $crate::__private::Source::into_source($iter).filter_map($($mv)? |$item| ::core::option::Option::flatten($body))
+    };
+    // Binds the variables of outer levels from clones of their items
+    (@outer $([[$opat:pat] $ovar:ident])*) => {
+        $(
+        // Cloned again, so that inner levels can take ownership of it
+        let $ovar = ::core::clone::Clone::clone(&$ovar);
+        #[allow(unused_variables)]
+        let $opat = ::core::clone::Clone::clone(&$ovar);
+        )*
+    };
+    // Evaluates to `Some(Some(keep))` if the item is kept, `Some(None)` if it is skipped,
+    // and `None` if the level must stop
+    (@clauses [$keep:expr] $acc:ident) => {
+        ::core::option::Option::Some(::core::option::Option::Some($keep))
+    };
+    (@clauses [$keep:expr] $acc:ident [if let $lpat:pat = $lexp:expr] $($cl:tt)*) => {
+        if let $lpat = $lexp {
+            $crate::iter!(@clauses [$keep] $acc $($cl)*)
+        } else {
+            ::core::option::Option::Some(::core::option::Option::None)
+        }
+    };
+    (@clauses [$keep:expr] $acc:ident [let $lpat:pat = $lexp:expr] $($cl:tt)*) => {{
+        let $lpat = $lexp;
+        $crate::iter!(@clauses [$keep] $acc $($cl)*)
+    }};
+    (@clauses [$keep:expr] $acc:ident [if $ifexp:expr] $($cl:tt)*) => {
+        if $ifexp {
+            $crate::iter!(@clauses [$keep] $acc $($cl)*)
+        } else {
+            ::core::option::Option::Some(::core::option::Option::None)
+        }
+    };
+    (@clauses [$keep:expr] $acc:ident [state $state:ident = $init:expr] $($cl:tt)*) => {{
+        let $state = $acc;
+        $crate::iter!(@clauses [$keep] $acc $($cl)*)
+    }};
+    (@clauses [$keep:expr] $acc:ident [scan $state:ident = $init:expr => $update:expr] $($cl:tt)*) => {{
+        let $state = $acc.take()?;
+        let $state = $update;
+        *$acc = ::core::option::Option::Some(::core::clone::Clone::clone(&$state));
+        $crate::iter!(@clauses [$keep] $acc $($cl)*)
+    }};
+    (@clauses [$keep:expr] $acc:ident [while $whilexp:expr] $($cl:tt)*) => {
+        if $whilexp {
+            $crate::iter!(@clauses [$keep] $acc $($cl)*)
+        } else {
+            ::core::option::Option::None
+        }
+    };
+    // Below are alternative ways for calling this macro
+    //
+    (@[$($mv:tt)?] $exp:expr; if $ifexp:expr; for $($for:tt)*) => {
+        $crate::iter!(@[$($mv)?] $exp; for $($for)*; if $ifexp)
+    };
+    (@[$($mv:tt)?] $exp:expr; if $ifexp:expr; else $elsexp:expr; for $($for:tt)*) => {
+        $crate::iter!(@[$($mv)?] $exp; for $($for)*; if $ifexp; else $elsexp)
+    };
+    (@[$($mv:tt)?] $exp:expr; while $whilexp:expr; for $($for:tt)*) => {
+        $crate::iter!(@[$($mv)?] $exp; for $($for)*; while $whilexp)
+    };
+    // [f(x, k); for x, k in iter, scalar k], only reached when a source is not an expression
+    (@[$($mv:tt)?] $exp:expr; for $first:ident $(, $rest:ident)+ in $($tail:tt)+) => {
+        $crate::iter!(@broadcast [@[$($mv)?] $exp; for $first $(, $rest)+ in] [] [] $($tail)+)
+    };
+    (@broadcast $head:tt [$($srcs:tt)*] $seen:tt scalar $value:expr, $($rest:tt)+) => {
+        $crate::iter!(@broadcast $head [$($srcs)* ::core::iter::repeat($value),] [scalar] $($rest)+)
+    };
+    (@broadcast $head:tt [$($srcs:tt)*] $seen:tt scalar $value:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@broadcast $head [$($srcs)* ::core::iter::repeat($value)] [scalar] $(; $($rest)*)?)
+    };
+    (@broadcast $head:tt [$($srcs:tt)*] $seen:tt $src:expr, $($rest:tt)+) => {
+        $crate::iter!(@broadcast $head [$($srcs)* $src,] $seen $($rest)+)
+    };
+    (@broadcast $head:tt [$($srcs:tt)*] $seen:tt $src:expr $(; $($rest:tt)*)?) => {
+        $crate::iter!(@broadcast $head [$($srcs)* $src] $seen $(; $($rest)*)?)
+    };
+    (@broadcast [$($head:tt)*] [$($srcs:tt)*] [scalar] $(; $($rest:tt)*)?) => {
+        $crate::iter!($($head)* $($srcs)* $(; $($rest)*)?)
+    };
+    (@broadcast $($body:tt)*) => {
+        ::core::compile_error!("invalid comprehension syntax, refer to the documentation of `iter!`")
+    };
+    (@[$($mv:tt)?] $($body:tt)*) => {
+        ::core::compile_error!("invalid comprehension syntax, refer to the documentation of `iter!`")
+    };
+    // [try f(x)?; ...], where `?` returns the error as the item
+    (@try [$($mv:tt)?] $exp:expr; $($rest:tt)+) => {
+        $crate::Comprehension::new($crate::iter!(@[$($mv)?] (|| ::core::result::Result::Ok($exp))(); $($rest)+))
+    };
+    (try $($body:tt)+) => {
+        $crate::iter!(@try [] $($body)+)
+    };
+    (move try $($body:tt)+) => {
+        $crate::iter!(@try [move] $($body)+)
+    };
+    // [move f(x); ...], where all closures take ownership of the variables they use
+    (move $($body:tt)+) => {
+        $crate::Comprehension::new($crate::iter!(@[move] $($body)+))
+    };
+    ($($body:tt)+) => {
+        $crate::Comprehension::new($crate::iter!(@[] $($body)+))
+    };
+}
+
+/// Join two sources on equal keys using Python's list-comprehension style.
+///
+/// Instead of comparing every pair of items, an index of the right source is built with a
+/// `HashMap`, so the join runs in linear time. Returns an iterator over the mapped pairs,
+/// in the order of the left source. Key expressions use references. Both sources must
+/// have [`Clone`] items: each emitted pair clones the item of the right source, which stays
+/// in the index, and the item of the left source is cloned for all its matches but the last.
+/// Joining sources requires the `std` feature.
+///
+/// # Basic usage
+///
+#[cfg_attr(feature = "std", doc = "```rust")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// # #[macro_use] extern crate comptools;
+/// let users = [(1, "alice"), (2, "bob"), (3, "carol")];
+/// let orders = [(1, "book"), (3, "pen"), (1, "lamp")];
+/// // join![f(a, b); for a in left; for b in right; on key(a) == key(b)];
+/// let iter = join![(a.1, b.1); for a in users.iter(); for b in orders.iter(); on a.0 == b.0];
+/// assert_eq!(iter.collect::<Vec<_>>(), vec![("alice", "book"), ("alice", "lamp"), ("carol", "pen")]);
+/// ```
+///
+/// Joining values into a string is done by [`join_str`](macro@join_str) instead.
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! join {
+    ($exp:expr; for $a:ident in $left:expr; for $b:ident in $right:expr; on $($on:tt)+) => {
+        $crate::join!(@on [$exp; $a; $left; $b; $right] [] $($on)+)
+    };
+    (@on [$exp:expr; $a:ident; $left:expr; $b:ident; $right:expr] [$($lkey:tt)+] == $($rkey:tt)+) => {
+        $crate::Comprehension::new(
+            $crate::__private::hash_join($left, $right, |$a| $($lkey)+, |$b| $($rkey)+).map(|($a, $b)| $exp),
+        )
+    };
+    (@on [$($head:tt)*] [$($lkey:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::join!(@on [$($head)*] [$($lkey)* $next] $($rest)*)
+    };
+    (@on $head:tt $lkey:tt) => {
+        ::core::compile_error!("expected an `on key(a) == key(b)` clause")
+    };
+    ($($body:tt)*) => {
+        ::core::compile_error!("expected `join![f(a, b); for a in left; for b in right; on key(a) == key(b)]`, use `join_str!` to join values into a string")
+    };
+}
+
+/// Join the values of a comprehension into a string using Python's list-comprehension style.
+///
+/// The values are formatted with [`Display`](std::fmt::Display) into a single `String`,
+/// separated by the separator, like Python's `sep.join(...)`. No intermediate collection
+/// is allocated. The separator can be any expression implementing `Display`.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // join_str![sep; f(x); for x in iter];
+/// let s = join_str![", "; x * x; for x in 1..5];
+/// assert_eq!(s, "1, 4, 9, 16");
+/// let sep = '-';
+/// assert_eq!(join_str![sep; c; for c in "abc".chars()], "a-b-c");
+/// // Same as sep.join(str(f(x)) for x in iter) in Python
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! join_str {
+    ($sep:expr; $($body:tt)+) => {{
+    $crate::__private::join_display($crate::iter![$($body)+], $sep)
+    }};
+}
+
+/// Return sum of values of an iterator using Python's list-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // sum![f(x); for x in iter];
+/// // Create an iterator and sum its values
+/// let sum: u64 = sum![x*x; for x in 1..10];
+/// assert_eq!(sum, 285);
+/// // Same as iter![...].sum()
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! sum {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).sum()
+    }};
+}
+
+/// Return product of values of an iterator using Python's list-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // product![f(x); for x in iter];
+/// // Create an iterator and multiply its values
+/// let product: u64 = product![x*x; for x in 1..10];
+/// assert_eq!(product, 131681894400);
+/// // Same as iter![...].product()
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! product {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).product()
+    }};
+}
+
+/// Fold the values of an iterator using Python's list-comprehension style.
+///
+/// The step `acc, x => g(acc, x)` combines the accumulator, starting at `init`, with each
+/// value. When the comprehension iterates over a single variable, its expression can be
+/// omitted, and the values are the ones of that variable.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // fold![acc, x => g(acc, x); init i; for x in iter];
+/// // Create an iterator and fold its values
+/// let n = fold![acc, d => acc * 10 + d; init 0; for d in [1, 2, 3].into_iter()];
+/// assert_eq!(n, 123);
+/// // fold![acc, y => g(acc, y); init i; f(x); for x in iter];
+/// let s = fold![s, w => s + &w; init String::new(); w.to_uppercase(); for w in ["a", "b"].into_iter()];
+/// assert_eq!(s, "AB");
+/// // Same as functools.reduce(g, iter, init) in Python
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! fold {
+    ($acc:pat, $value:pat => $step:expr; init $init:expr; for $item:ident in $($body:tt)*) => {{
+    ($crate::iter![$item; for $item in $($body)*]).fold($init, |$acc, $value| $step)
+    }};
+    ($acc:pat, $value:pat => $step:expr; init $init:expr; $($body:tt)*) => {{
+    ($crate::iter![$($body)*]).fold($init, |$acc, $value| $step)
+    }};
+}
+
+/// Reduce the values of an iterator using Python's list-comprehension style.
+///
+/// Like [`fold`](macro@fold), but the first value is the initial accumulator. Returns
+/// `None` if there is no value.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // reduce![acc, x => g(acc, x); for x in iter];
+/// // Create an iterator and reduce its values
+/// let gcd = reduce![a, b => (1..=a.min(b)).rev().find(|d| a % d == 0 && b % d == 0).unwrap(); for x in [12, 18, 30].into_iter()];
+/// assert_eq!(gcd, Some(6));
+/// // Same as functools.reduce(g, iter) in Python
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! reduce {
+    ($acc:pat, $value:pat => $step:expr; for $item:ident in $($body:tt)*) => {{
+    ($crate::iter![$item; for $item in $($body)*]).reduce(|$acc, $value| $step)
+    }};
+    ($acc:pat, $value:pat => $step:expr; $($body:tt)*) => {{
+    ($crate::iter![$($body)*]).reduce(|$acc, $value| $step)
+    }};
+}
+
+/// Return the maximum value of an iterator using Python's list-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // max![f(x); for x in iter];
+/// // Create an iterator and return its maximum value, if any
+/// let max = max![x % 7; for x in 1..10];
+/// assert_eq!(max, Some(6));
+/// // Same as iter![...].max()
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! max {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).max()
+    }};
+}
+
+/// Return the minimum value of an iterator using Python's list-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // min![f(x); for x in iter];
+/// // Create an iterator and return its minimum value, if any
+/// let min = min![x % 7; for x in 3..10];
+/// assert_eq!(min, Some(0));
+/// // Same as iter![...].min()
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! min {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).min()
+    }};
+}
+
+/// Return the minimum and the maximum values of an iterator using Python's list-comprehension style.
+///
+/// Both are computed in a single pass, and values must implement [`Clone`]. Like
+/// [`min`](macro@min) and [`max`](macro@max), the first minimum and the last maximum are returned.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // minmax![f(x); for x in iter];
+/// // Create an iterator and return its extreme values, if any
+/// let minmax = minmax![x % 7; for x in 3..10];
+/// assert_eq!(minmax, Some((0, 6)));
+/// // Same as (iter![...].min(), iter![...].max()), but in one pass
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! minmax {
+    ($($body:tt)*) => {{
+    $crate::__private::minmax($crate::iter![$($body)*])
+    }};
+}
+
+/// Return the index of the maximum value of an iterator using Python's list-comprehension style.
+///
+/// The index is the position of the value among the values of the comprehension, starting
+/// from zero. Like NumPy's `argmax`, the first maximum is returned. Returns `None` if there is no value.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // argmax![f(x); for x in iter];
+/// // Create an iterator and return the index of its maximum value, if any
+/// let words = ["a", "ccc", "bb", "ddd"];
+/// let index = argmax![w.len(); for w in words.iter()];
+/// assert_eq!(index, Some(1));
+/// // Same as iter![...].enumerate().max_by_key(...), but keeping the first maximum
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! argmax {
+    ($($body:tt)*) => {{
+    $crate::__private::argmax($crate::iter![$($body)*])
+    }};
+}
+
+/// Return the index of the minimum value of an iterator using Python's list-comprehension style.
+///
+/// The index is the position of the value among the values of the comprehension, starting
+/// from zero. Like NumPy's `argmin`, the first minimum is returned. Returns `None` if there is no value.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // argmin![f(x); for x in iter];
+/// // Create an iterator and return the index of its minimum value, if any
+/// let index = argmin![(x - 4_i32).abs(); for x in [1, 3, 5, 7].into_iter()];
+/// assert_eq!(index, Some(1));
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! argmin {
+    ($($body:tt)*) => {{
+    $crate::__private::argmin($crate::iter![$($body)*])
+    }};
+}
+
+/// Return the index of the first item satisfying a condition using Python's list-comprehension style.
+///
+/// Iteration stops at the first item passing the clauses. The index is the position of the
+/// item in the source, starting from zero. Returns `None` if no item passes the clauses.
+///
+/// There is no expression: the condition is written as an `if` clause.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // position![for x in iter; if cond(x)];
+/// // Find the first word starting with a vowel
+/// let words = ["the", "quick", "orange", "fox", "is", "agile"];
+/// let index = position![for w in words.iter(); if w.starts_with(['a', 'e', 'i', 'o', 'u'])];
+/// assert_eq!(index, Some(2));
+/// // Same as next((i for i, x in enumerate(iter) if cond(x)), None) in Python
+/// ```
+///
+/// Any clause can be used, e.g., several conditions or a `let` binding:
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // position![for x in iter; let y = f(x); if cond(y); ...];
+/// let index = position![for x in 1..; let y = x * x; if y > 50; if y % 2 == 0];
+/// assert_eq!(index, Some(7));
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! position {
+    (for $item:pat in $iter:expr $(; $($rest:tt)*)?) => {{
+    ($crate::iter![i; for (i, $item) in ::core::iter::Iterator::enumerate(::core::iter::IntoIterator::into_iter($iter)) $(; $($rest)*)?]).next()
+    }};
+    ($exp:expr; for $($body:tt)*) => {
+        ::core::compile_error!("`position!` takes no expression, write the condition as an `if` clause")
+    };
+}
+
+/// Return the index of the last item satisfying a condition using Python's list-comprehension style.
+///
+/// Iteration starts from the end and stops at the first item passing the clauses. The index
+/// is the position of the item in the source, counted from the start. Returns `None` if no
+/// item passes the clauses.
+///
+/// The source must be double-ended and of known length, like slices or ranges, and the
+/// comprehension cannot contain `while` clauses. There is no expression: the condition is
+/// written as an `if` clause.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // rposition![for x in iter; if cond(x)];
+/// // Find the last even number
+/// let index = rposition![for x in [2, 4, 5, 8, 9].iter(); if x % 2 == 0];
+/// assert_eq!(index, Some(3));
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! rposition {
+    (for $item:pat in $iter:expr $(; $($rest:tt)*)?) => {{
+    ($crate::iter![i; for (i, $item) in ::core::iter::Iterator::enumerate(::core::iter::IntoIterator::into_iter($iter)) $(; $($rest)*)?]).next_back()
+    }};
+    ($exp:expr; for $($body:tt)*) => {
+        ::core::compile_error!("`rposition!` takes no expression, write the condition as an `if` clause")
+    };
+}
+
+/// Return the first value of an iterator using Python's list-comprehension style.
+///
+/// Values after the first one are never computed.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // first![f(x); for x in iter];
+/// // Create an iterator and return its first value, if any
+/// let first = first![x * x; for x in 1..10; if x % 4 == 0];
+/// assert_eq!(first, Some(16));
+/// // Same as next((f(x) for x in iter), None) in Python
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! first {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).next()
+    }};
+}
+
+/// Return the last value of an iterator using Python's list-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // last![f(x); for x in iter];
+/// // Create an iterator and return its last value, if any
+/// let last = last![x * x; for x in 1..10; if x % 4 == 0];
+/// assert_eq!(last, Some(64));
+/// // Same as iter![...].last()
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! last {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).last()
+    }};
+}
+
+/// Count the items of an iterator using Python's list-comprehension style.
+///
+/// As only the clauses matter, the expression can be omitted.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // count![for x in iter; if cond(x)];
+/// // Count the items passing the clauses
+/// let count = count![for x in 1..10; if x % 3 == 0];
+/// assert_eq!(count, 3);
+/// // Same as iter![...].count(), or sum(1 for x in iter if cond(x)) in Python
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! count {
+    (for $item:pat in $iter:expr) => {{
+    $crate::__private::Source::into_source($iter).count()
+    }};
+    (for $($body:tt)*) => {{
+    ($crate::iter![(); for $($body)*]).count()
+    }};
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).count()
+    }};
+}
+
+/// Check if any value of an iterator is `true` using Python's list-comprehension style.
+///
+/// Iteration stops at the first `true` value.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // any![cond(x); for x in iter];
+/// // Check if any item satisfies a condition
+/// let any = any![x * x > 50; for x in 1..];
+/// assert!(any);
+/// // Same as iter![...].any(|b| b)
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! any {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).any(::core::convert::identity)
+    }};
+}
+
+/// Check if all values of an iterator are `true` using Python's list-comprehension style.
+///
+/// Iteration stops at the first `false` value.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // all![cond(x); for x in iter; if pre(x)];
+/// // Check if all items passing the clauses satisfy a condition
+/// let all = all![x % 4 == 0; for x in 1..10; if x % 2 == 0];
+/// assert!(!all);
+/// // Same as iter![...].all(|b| b)
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! all {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).all(::core::convert::identity)
+    }};
+}
+
+/// Create a sorted `Vec` using Python's list-comprehension style.
+///
+/// The sort is stable. With a trailing `by` clause, items are sorted by the given key, which
+/// can use the variables of the comprehension, and is computed once per item.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // sorted![f(x); for x in iter];
+/// // Create a Vec and sort it
+/// let vec = sorted![x % 4; for x in 1..10];
+/// assert_eq!(vec, vec![0, 0, 1, 1, 1, 2, 2, 3, 3]);
+/// // sorted![f(x); for x in iter; by key(x)];
+/// let words = sorted![w; for w in "a bbb cc".split(' '); by w.len()];
+/// assert_eq!(words, vec!["a", "cc", "bbb"]);
+/// // Same as sorted(f(x) for x in iter) in Python
+/// ```
+///
+/// # Order
+///
+/// A trailing `desc` clause sorts in descending order, also after `by`, like Python's
+/// `reverse=True`: equal items keep their original order. Alternatively, `by cmp` sorts with
+/// a comparison function of two references to the values.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // sorted![f(x); for x in iter; desc];
+/// let vec = sorted![x % 4; for x in 1..6; desc];
+/// assert_eq!(vec, vec![3, 2, 1, 1, 0]);
+/// // sorted![f(x); for x in iter; by desc key(x)];
+/// let words = sorted![w; for w in "a bbb cc dd".split(' '); by desc w.len()];
+/// assert_eq!(words, vec!["bbb", "cc", "dd", "a"]);
+/// // sorted![f(x); for x in iter; by cmp |a, b| cmp(a, b)];
+/// let floats = sorted![x; for x in [0.5_f64, -1.0, 0.25].into_iter(); by cmp |a, b| a.total_cmp(b)];
+/// assert_eq!(floats, vec![-1.0, 0.25, 0.5]);
+/// ```
+///
+/// # Expensive keys
+///
+/// Keys given with `by` are already cached, like with
+/// [`sort_by_cached_key`](slice::sort_by_cached_key): each item is paired with its key while
+/// collecting, so the key is computed exactly once per item, and never during the sort.
+/// Expensive keys, e.g., lowercased strings, need no separate mode.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // sorted![f(x); for x in iter; by expensive_key(x)];
+/// let words = sorted![w; for w in "b A c".split(' '); by w.to_lowercase()];
+/// assert_eq!(words, vec!["A", "b", "c"]);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! sorted {
+    (@split [$($body:tt)*] ; desc) => {
+        $crate::sorted!(@split [$($body)*] ; by cmp |a, b| ::core::cmp::Ord::cmp(b, a))
+    };
+    (@split [$($body:tt)*] ; by cmp $cmp:expr) => {{
+    let mut items: $crate::__private::Vec<_> = ($crate::iter![$($body)*]).collect();
+    items.sort_by($cmp);
+    items
+    }};
+    (@split [$($body:tt)*] ; by desc $key:expr) => {
+        $crate::sorted!(@key [$key] [reverse] $($body)*)
+    };
+    (@split [$($body:tt)*] ; by $key:expr) => {
+        $crate::sorted!(@key [$key] [] $($body)*)
+    };
+    (@key [$key:expr] [$($rev:ident)?] $exp:expr; $($body:tt)*) => {{
+    let mut items: $crate::__private::Vec<(_, _)> = ($crate::iter![($key, $exp); $($body)*]).collect();
+    items.sort_by(|a, b| ::core::cmp::Ord::cmp(&a.0, &b.0)$(.$rev())?);
+    items.into_iter().map(|(_, item)| item).collect::<$crate::__private::Vec<_>>()
+    }};
+    (@split [$($body:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::sorted!(@split [$($body)* $next] $($rest)*)
+    };
+    (@split [$($body:tt)*]) => {{
+    let mut items: $crate::__private::Vec<_> = ($crate::iter![$($body)*]).collect();
+    items.sort();
+    items
+    }};
+    ($($body:tt)*) => {
+        $crate::sorted!(@split [] $($body)*)
+    };
+}
+
+/// Return the `n` largest values of an iterator using Python's list-comprehension style.
+///
+/// Values are returned in a `Vec`, in descending order. Only `n` values are kept in a heap
+/// while iterating, so this runs in `O(len log n)` time, without sorting all the values.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // nlargest![n; f(x); for x in iter];
+/// // Keep the three longest words
+/// let words = "the quick brown fox jumps over the lazy dog";
+/// let longest = nlargest![3; (w.len(), w); for w in words.split(' ')];
+/// assert_eq!(longest, vec![(5, "quick"), (5, "jumps"), (5, "brown")]);
+/// // Same as heapq.nlargest(n, ...) in Python
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! nlargest {
+    ($n:expr; $($body:tt)*) => {{
+    $crate::__private::nlargest($n, $crate::iter![$($body)*])
+    }};
+}
+
+/// Return the `n` smallest values of an iterator using Python's list-comprehension style.
+///
+/// Values are returned in a `Vec`, in ascending order. Only `n` values are kept in a heap
+/// while iterating, so this runs in `O(len log n)` time, without sorting all the values.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // nsmallest![n; f(x); for x in iter];
+/// let smallest = nsmallest![2; (x * 7) % 10; for x in 1..10];
+/// assert_eq!(smallest, vec![1, 2]);
+/// // Same as heapq.nsmallest(n, ...) in Python
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! nsmallest {
+    ($n:expr; $($body:tt)*) => {{
+    $crate::__private::nsmallest($n, $crate::iter![$($body)*])
+    }};
+}
+
+/// Return descriptive statistics of an iterator using Python's list-comprehension style.
+///
+/// The count, sum, minimum, maximum, mean, and standard deviation are computed in a single
+/// pass, and returned as a [`Summary`](stats::Summary). Values can be of any primitive numeric
+/// type, and are converted to `f64` with [`IntoFloat`](stats::IntoFloat).
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // summary![f(x); for x in iter];
+/// // Create an iterator and describe its values
+/// let summary = summary![x*x; for x in 1..=4u32];
+/// assert_eq!(summary.count(), 4);
+/// assert_eq!(summary.sum(), 30.0);
+/// assert_eq!(summary.min(), Some(1.0));
+/// assert_eq!(summary.max(), Some(16.0));
+/// assert_eq!(summary.mean(), Some(7.5));
+/// // Same as iter![...].collect::<Summary>()
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! summary {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).collect::<$crate::stats::Summary>()
+    }};
+}
+
+/// Assign the values of an iterator to equal-width bins using Python's list-comprehension style.
+///
+/// The values are collected, converted to `f64` with [`IntoFloat`](stats::IntoFloat), and
+/// split into `n` bins of equal width spanning their range, as with [`stats::bin`]. Returns the `(value, bin)` pairs.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // bin![n; f(x); for x in iter];
+/// // Create an iterator and split its values into 2 bins
+/// let bins = bin![2; x*x; for x in 0..4u32];
+/// assert_eq!(bins, vec![(0.0, 0), (1.0, 0), (4.0, 0), (9.0, 1)]);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! bin {
+    ($n:expr; $($body:tt)*) => {{
+    $crate::stats::bin(
+        ::core::iter::Iterator::map($crate::iter![$($body)*], $crate::__private::IntoFloat::<f64>::into_float).collect(),
+        $n,
+    )
+    }};
+}
+
+/// Assign the values of an iterator to quantile-based bins using Python's list-comprehension style.
+///
+/// The values are collected, converted to `f64` with [`IntoFloat`](stats::IntoFloat), and
+/// split into `n` bins holding about the same number of values, as with [`stats::qcut`]. Returns the `(value, bin)` pairs.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // qcut![n; f(x); for x in iter];
+/// // Create an iterator and split its values into 2 bins of equal size
+/// let bins = qcut![2; x*x; for x in 0..4u32];
+/// assert_eq!(bins, vec![(0.0, 0), (1.0, 0), (4.0, 1), (9.0, 1)]);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! qcut {
+    ($n:expr; $($body:tt)*) => {{
+    $crate::stats::qcut(
+        ::core::iter::Iterator::map($crate::iter![$($body)*], $crate::__private::IntoFloat::<f64>::into_float).collect(),
+        $n,
+    )
+    }};
+}
+
+/// Return sum of the non-NaN values of an iterator using Python's list-comprehension style.
+///
+/// Works with `f32` and `f64`, like NumPy's `nansum`: NaN values are skipped, and the sum
+/// of an iterator without any non-NaN value is zero.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // nansum![f(x); for x in iter];
+/// // Create an iterator and sum its non-NaN values
+/// let sum: f64 = nansum![x.sqrt(); for x in [4.0_f64, -1.0, 9.0].into_iter()];
+/// assert_eq!(sum, 5.0); // sqrt(-1.0) is NaN
+/// ```
+///
+/// # Counting NaN values
+///
+/// With a leading `count nan;`, the number of NaN values skipped is also returned, as
+/// `(sum, skipped)`, e.g., to report how much data was dropped. Only the NaN values passing
+/// the clauses are counted, and comparisons with NaN are false, so conditions like `if x < 1.0`
+/// already drop them.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // nansum![count nan; f(x); for x in iter];
+/// let (sum, skipped) = nansum![count nan; x.sqrt(); for x in [4.0_f64, -1.0, 9.0].into_iter()];
+/// assert_eq!((sum, skipped), (5.0, 1));
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! nansum {
+    (count nan; $($body:tt)*) => {{
+    $crate::__private::nansum_counted($crate::iter![$($body)*])
+    }};
+    ($($body:tt)*) => {{
+    $crate::__private::nansum($crate::iter![$($body)*])
+    }};
+}
+
+/// Return the mean of the values of an iterator using Python's list-comprehension style.
+///
+/// Values can be of any primitive numeric type, and are converted to `f64` as with `as`, or to
+/// the float type given with a leading `as f32;`.
+/// Returns `None` if there is no value. The sum and the count are computed in a single pass.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // mean![f(x); for x in iter];
+/// // Create an iterator and average its values
+/// let mean = mean![x * 2; for x in 1..5_u32];
+/// assert_eq!(mean, Some(5.0));
+/// // mean![as T; f(x); for x in iter];
+/// let mean = mean![as f32; x; for x in 1..3_u8];
+/// assert_eq!(mean, Some(1.5_f32));
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! mean {
+    (as $float:ty; $($body:tt)*) => {{
+    $crate::__private::mean::<_, $float>($crate::iter![$($body)*])
+    }};
+    ($($body:tt)*) => {{
+    $crate::__private::mean::<_, f64>($crate::iter![$($body)*])
+    }};
+}
+
+/// Return the mean of the non-NaN values of an iterator using Python's list-comprehension style.
+///
+/// Works with `f32` and `f64`, like NumPy's `nanmean`: NaN values are skipped. Returns `None`
+/// if there is no non-NaN value. The sum and the count are computed in a single pass.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // nanmean![f(x); for x in iter];
+/// // Create an iterator and average its non-NaN values
+/// let mean: Option<f64> = nanmean![x.sqrt(); for x in [4.0_f64, -1.0, 16.0].into_iter()];
+/// assert_eq!(mean, Some(3.0)); // sqrt(-1.0) is NaN
+/// ```
+///
+/// # Counting NaN values
+///
+/// With a leading `count nan;`, the number of NaN values skipped is also returned, as
+/// `(mean, skipped)`.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // nanmean![count nan; f(x); for x in iter];
+/// let (mean, skipped) = nanmean![count nan; x; for x in [f64::NAN, 1.0, f64::NAN, 2.0].into_iter()];
+/// assert_eq!((mean, skipped), (Some(1.5), 2));
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! nanmean {
+    (count nan; $($body:tt)*) => {{
+    $crate::__private::nanmean_counted($crate::iter![$($body)*])
+    }};
+    ($($body:tt)*) => {{
+    $crate::__private::nanmean($crate::iter![$($body)*])
+    }};
+}
+
+/// Return the maximum float of an iterator using Python's list-comprehension style.
+///
+/// Works with `f32` and `f64`, for which [`Iterator::max`] is not available. NaN values
+/// are skipped, and the other values are compared with `total_cmp`, so `-0.0 < 0.0`.
+/// Returns `None` if there is no non-NaN value.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // fmax![f(x); for x in iter];
+/// // Create an iterator and return its largest value
+/// let max: Option<f64> = fmax![x.sqrt(); for x in [4.0_f64, -1.0, 9.0, 1.0].into_iter()];
+/// assert_eq!(max, Some(3.0)); // sqrt(-1.0) is NaN
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! fmax {
+    ($($body:tt)*) => {{
+    $crate::__private::fmax($crate::iter![$($body)*])
+    }};
+}
+
+/// Return the minimum float of an iterator using Python's list-comprehension style.
+///
+/// Works with `f32` and `f64`, for which [`Iterator::min`] is not available. NaN values
+/// are skipped, and the other values are compared with `total_cmp`, so `-0.0 < 0.0`.
+/// Returns `None` if there is no non-NaN value.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // fmin![f(x); for x in iter];
+/// // Create an iterator and return its smallest value
+/// let min: Option<f64> = fmin![x.sqrt(); for x in [4.0_f64, -1.0, 9.0, 1.0].into_iter()];
+/// assert_eq!(min, Some(1.0)); // sqrt(-1.0) is NaN
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! fmin {
+    ($($body:tt)*) => {{
+    $crate::__private::fmin($crate::iter![$($body)*])
+    }};
+}
+
+/// Return one page of the values of an iterator using Python's list-comprehension style.
+///
+/// Pages are numbered from zero, so `page 0 of size 20` holds the first 20 values. The
+/// page number must be a single token, e.g., a literal or a variable, so wrap more complex
+/// expressions in parentheses. Returns the values of the page, and whether more values
+/// remain after it. Values after the page are not computed, except for the first one.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // paginate![page p of size n; f(x); for x in iter];
+/// // Return the third page of 3 values
+/// let (page, more) = paginate![page 2 of size 3; x*x; for x in 0..10];
+/// assert_eq!(page, vec![36, 49, 64]);
+/// assert!(more);
+/// let (page, more) = paginate![page 3 of size 3; x*x; for x in 0..10];
+/// assert_eq!(page, vec![81]);
+/// assert!(!more);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! paginate {
+    (page $page:tt of size $size:expr; $($body:tt)*) => {{
+    #[allow(unused_parens)]
+    let page: usize = $page;
+    $crate::__private::paginate($crate::iter![$($body)*], page, $size)
+    }};
+}
+
+/// Group the values of an iterator by key using Python's list-comprehension style.
+///
+/// Values are bucketed into a `HashMap<K, Vec<V>>`, and keep their original order within
+/// each group. See [`group_by_ordered`](macro@group_by_ordered) to iterate groups in key order.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // group_by![key(x) => value(x); for x in iter];
+/// // Group numbers by parity
+/// let groups = group_by![x % 2 == 0 => x; for x in 1..10; if x > 3];
+/// assert_eq!(groups[&true], vec![4, 6, 8]);
+/// assert_eq!(groups[&false], vec![5, 7, 9]);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! group_by {
+    ($key:expr => $value:expr; $($body:tt)*) => {{
+    $crate::__private::group_by($crate::iter![($key, $value); $($body)*])
+    }};
+}
+
+/// Count the occurrences of the values of an iterator using Python's list-comprehension style.
+///
+/// Returns a `HashMap<K, usize>` mapping each distinct value to its number of occurrences.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // counter![f(x); for x in iter];
+/// // Count letters
+/// let counts = counter![c; for c in "banana".chars(); if c != 'b'];
+/// assert_eq!(counts[&'a'], 3);
+/// assert_eq!(counts[&'n'], 2);
+/// assert_eq!(counts.get(&'b'), None);
+/// // Same as collections.Counter(...) in Python
+/// ```
+///
+/// # Custom hasher
+///
+/// A leading `as HashMap<_, usize, S>;` uses the hasher built by `S`, which must implement
+/// [`BuildHasher`](std::hash::BuildHasher) and [`Default`].
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::collections::HashMap;
+/// use std::hash::{BuildHasherDefault, DefaultHasher};
+///
+/// type Hasher = BuildHasherDefault<DefaultHasher>;
+/// // counter![as HashMap<_, usize, S>; f(x); for x in iter];
+/// let counts = counter![as HashMap<_, usize, Hasher>; c; for c in "banana".chars()];
+/// assert_eq!(counts[&'a'], 3);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! counter {
+    (as $map:ty; $($body:tt)*) => {{
+    let counts: $map = $crate::__private::counter($crate::iter![$($body)*]);
+    counts
+    }};
+    ($($body:tt)*) => {
+        $crate::counter!(as ::std::collections::HashMap<_, usize>; $($body)*)
+    };
+}
+
+/// Group the values of an iterator by key using Python's list-comprehension style.
+///
+/// Values are bucketed into a `BTreeMap<K, Vec<V>>`, so groups are iterated in key order,
+/// and values keep their original order within each group.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // group_by_ordered![key(x) => value(x); for x in iter];
+/// // Group words by length
+/// let groups = group_by_ordered![w.len() => w; for w in ["ab", "c", "de", "fgh"].into_iter()];
+/// assert_eq!(groups.into_iter().collect::<Vec<_>>(), vec![
+///     (1, vec!["c"]),
+///     (2, vec!["ab", "de"]),
+///     (3, vec!["fgh"]),
+/// ]);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! group_by_ordered {
+    ($key:expr => $value:expr; $($body:tt)*) => {{
+    $crate::__private::group_by_ordered($crate::iter![($key, $value); $($body)*])
+    }};
+}
+
+/// Create a pivot table using Python's list-comprehension style.
+///
+/// Values are aggregated by row and column keys into a `HashMap<R, HashMap<C, A>>`, in a
+/// single pass. The available aggregations are `sum`, `min`, `max`, and `count`, the latter
+/// taking no value expression.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// let sales = [("north", 2021, 10), ("north", 2022, 20), ("south", 2021, 5), ("north", 2021, 7)];
+/// // pivot![row(x), col(x) => agg value(x); for x in iter];
+/// // Total sales per region and year
+/// let table = pivot![s.0, s.1 => sum s.2; for s in sales.iter()];
+/// assert_eq!(table["north"][&2021], 17);
+/// assert_eq!(table["south"][&2021], 5);
+/// // Number of sales per region and year
+/// let table = pivot![s.0, s.1 => count; for s in sales.iter()];
+/// assert_eq!(table["north"][&2021], 2);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! pivot {
+    ($row:expr, $col:expr => count; $($body:tt)*) => {
+        $crate::pivot![$row, $col => count (); $($body)*]
+    };
+    ($row:expr, $col:expr => $agg:ident $value:expr; $($body:tt)*) => {{
+    $crate::__private::pivot(
+        $crate::iter![($row, $col, $value); $($body)*],
+        $crate::__private::pivot_agg::$agg,
+    )
+    }};
+}
+
+/// Return sum of fallible values of an iterator using Python's list-comprehension style.
+///
+/// The expression must return a `Result`. Values are summed until the first error, which
+/// is returned instead of the sum.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // try_sum![f(x); for x in iter];
+/// // Parse and sum values
+/// let sum: Result<u64, _> = try_sum![x.parse::<u64>(); for x in "1 2 3".split(' ')];
+/// assert_eq!(sum, Ok(6));
+/// let sum: Result<u64, _> = try_sum![x.parse::<u64>(); for x in "1 x 3".split(' ')];
+/// assert!(sum.is_err());
+/// // Same as iter![...].sum::<Result<_, _>>()
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! try_sum {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).sum::<::core::result::Result<_, _>>()
+    }};
+}
+
+/// Return product of fallible values of an iterator using Python's list-comprehension style.
+///
+/// The expression must return a `Result`. Values are multiplied until the first error,
+/// which is returned instead of the product.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // try_product![f(x); for x in iter];
+/// // Parse and multiply values
+/// let product: Result<u64, _> = try_product![x.parse::<u64>(); for x in "2 3 4".split(' ')];
+/// assert_eq!(product, Ok(24));
+/// // Same as iter![...].product::<Result<_, _>>()
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! try_product {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).product::<::core::result::Result<_, _>>()
+    }};
+}
+
+/// Create a collection using Python's list-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // vect![f(x); for x in iter];
+/// // Create a collection
+/// let vec: Vec<u64> = vect![x*x; for x in 1..10];
+/// assert_eq!(vec, vec![1, 4, 9, 16, 25, 36, 49, 64, 81]);
+/// // Same as iter![...].collect()
+/// ```
+///
+/// # Collection type
+///
+/// The type of the collection can be given with a leading `as T;`, when it cannot be inferred.
+/// Any type implementing [`FromIterator`] can be used, including collections from other crates,
+/// e.g., `vect![as SmallVec<[u64; 8]>; ...]` to keep short comprehensions off the heap.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::collections::VecDeque;
+///
+/// // vect![as T; f(x); for x in iter];
+/// let len = vect![as Vec<_>; x; for x in 1..10; if x % 2 == 0].len();
+/// assert_eq!(len, 4);
+/// let deque = vect![as VecDeque<u64>; x*x; for x in 1..4];
+/// assert_eq!(deque.back(), Some(&9));
+/// // Same as iter![...].collect::<T>()
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! vect {
+    (as $target:ty; $($body:tt)*) => {{
+    ($crate::iter![$($body)*]).collect::<$target>()
+    }};
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).collect()
+    }};
+}
+
+/// Create an array using Python's list-comprehension style.
+///
+/// Unlike [`vect`](macro@vect), no allocation is needed, so arrays can be created in
+/// `no_std` crates without `alloc`.
+///
+/// # Basic usage
+///
+/// With a single source of the form `0..N`, where `N` is an integer literal, the array is
+/// built with [`core::array::from_fn`], and its length is checked at compile time.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // array![f(i); for i in 0..N];
+/// // Create an array of squares
+/// let squares = array![i * i; for i in 0..5];
+/// assert_eq!(squares, [0, 1, 4, 9, 16]);
+/// // Same as core::array::from_fn(|i| f(i))
+/// ```
+///
+/// # Constant contexts
+///
+/// With a leading `const;`, the array is instead built with a `while` loop, without any
+/// closure, so it can be used in `const` items and `const fn`. The expression is evaluated
+/// exactly once per index, so empty arrays never evaluate it.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// const fn square(i: usize) -> u64 {
+///     (i * i) as u64
+/// }
+/// // array![const; f(i); for i in 0..N];
+/// const SQUARES: [u64; 256] = array![const; square(i); for i in 0..256];
+/// assert_eq!(SQUARES[12], 144);
+/// ```
+///
+/// # Other sources
+///
+/// Any other comprehension is collected into an array whose length is inferred, e.g., from a
+/// type annotation. This includes ranges whose bound is not a literal, like a constant or a
+/// variable, which are then checked at runtime.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // array![f(x); for x in iter];
+/// let odd: [u64; 3] = array![x; for x in 1..; if x % 2 == 1; take 3];
+/// assert_eq!(odd, [1, 3, 5]);
+/// let n = 3;
+/// let squares: [usize; 3] = array![i * i; for i in 0..n];
+/// assert_eq!(squares, [0, 1, 4]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the comprehension does not yield exactly as many values as the length of the array.
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! array {
+    (const; $exp:expr; for $index:ident in 0..$len:expr) => {{
+    let mut array = $crate::__private::ArrayBuilder::<_, { $len }>::new();
+    while !array.is_full() {
+        let $index: usize = array.next_index();
+        array.push($exp);
+    }
+    array.finish()
+    }};
+    ($exp:expr; for $index:pat in 0..$len:literal) => {{
+    ::core::array::from_fn::<_, { $len }, _>(|$index| $exp)
+    }};
+    ($($body:tt)*) => {{
+    $crate::__private::array($crate::iter![$($body)*])
+    }};
+}
+
+/// Create a `VecDeque` using Python's list-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // deque![f(x); for x in iter];
+/// // Create a queue
+/// let mut queue = deque![x * x; for x in 1..5];
+/// assert_eq!(queue.pop_front(), Some(1));
+/// assert_eq!(queue.pop_back(), Some(16));
+/// // Same as collections.deque(...) in Python
+/// ```
+///
+/// # Reversing
+///
+/// A trailing `rev` clause pushes the values to the front of the queue instead of its back.
+/// Unlike with [`iter`](macro@iter), the comprehension does not need to be double-ended,
+/// so `rev` can follow clauses like `while` or `unique`.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // deque![f(x); for x in iter; rev];
+/// let deque = deque![x; for x in 1..; while x * x < 20; rev];
+/// assert_eq!(deque, [4, 3, 2, 1]);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! deque {
+    (@scan [$($body:tt)*] ; rev) => {{
+    $crate::__private::deque_front($crate::iter![$($body)*])
+    }};
+    (@scan [$($body:tt)*] $next:tt $($tail:tt)*) => {
+        $crate::deque!(@scan [$($body)* $next] $($tail)*)
+    };
+    (@scan [$($body:tt)*]) => {{
+    ($crate::iter![$($body)*]).collect::<$crate::__private::VecDeque<_>>()
+    }};
+    ($($body:tt)*) => {
+        $crate::deque!(@scan [] $($body)*)
+    };
+}
+
+/// Create a `String` using Python's list-comprehension style.
+///
+/// Values can be `char`s, string slices, or `String`s, which are concatenated without
+/// any intermediate collection.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // string![f(x); for x in iter];
+/// // Keep and capitalize letters
+/// let s = string![c.to_ascii_uppercase(); for c in "a1b2c3".chars(); if c.is_alphabetic()];
+/// assert_eq!(s, "ABC");
+/// let s = string![w; for w in "ab cd".split(' ')];
+/// assert_eq!(s, "abcd");
+/// // Same as "".join(...) in Python
+/// ```
+///
+/// See also [`join`](macro@join) to separate the values.
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! string {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).collect::<$crate::__private::String>()
+    }};
+}
+
+/// Create a `Vec` of `Vec`s using Python's nested list-comprehension style.
+///
+/// The first `for` clause iterates over the rows, and the following ones over the values
+/// of each row, which can use the variables of the row. Each level accepts its own clauses,
+/// e.g., to skip rows or values.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // matrix![f(i, j); for i in rows; for j in cols];
+/// // Create a multiplication table
+/// let table = matrix![i * j; for i in 1..4; for j in 1..3];
+/// assert_eq!(table, vec![vec![1, 2], vec![2, 4], vec![3, 6]]);
+/// // Same as [[f(i, j) for j in cols] for i in rows] in Python
+/// let triangle = matrix![j; for i in 0..4; if i > 0; for j in 0..i];
+/// assert_eq!(triangle, vec![vec![0], vec![0, 1], vec![0, 1, 2]]);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! matrix {
+    (@split [$exp:expr] [$($outer:tt)+] ; for $($inner:tt)+) => {{
+    ($crate::iter![
+        ($crate::iter![$exp; for $($inner)+]).collect::<$crate::__private::Vec<_>>();
+        for $($outer)+
+    ]).collect::<$crate::__private::Vec<_>>()
+    }};
+    (@split $exp:tt [$($outer:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::matrix!(@split $exp [$($outer)* $next] $($rest)*)
+    };
+    ($exp:expr; for $($rest:tt)+) => {
+        $crate::matrix!(@split [$exp] [] $($rest)+)
+    };
+}
+
+/// Create a [`Cow`](std::borrow::Cow) using Python's list-comprehension style,
+/// only allocating if the comprehension changes the source.
+///
+/// The source must be a slice, iterated by reference, or a string, iterated by `char`s.
+/// As long as the items produced equal the items of the source, nothing is allocated.
+/// If the items produced are a prefix of the source, e.g., with `while`, that prefix is borrowed.
+/// Otherwise, the collection is allocated when the first item differs.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::borrow::Cow;
+///
+/// // cow_vect![f(x); for x in slice];
+/// let data = [1_i32, 2, 3, 4];
+/// let abs = cow_vect![x.abs(); for x in &data];
+/// assert!(matches!(abs, Cow::Borrowed(_)));
+/// let even = cow_vect![*x; for x in &data; if *x % 2 == 0];
+/// assert_eq!(even, Cow::<[i32]>::Owned(vec![2, 4]));
+/// // Strings are iterated by chars
+/// let lower = cow_vect![c.to_ascii_lowercase(); for c in "hello"];
+/// assert!(matches!(lower, Cow::Borrowed("hello")));
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! cow_vect {
+    ($exp:expr; for $item:pat in $source:expr $(; $($rest:tt)*)?) => {{
+    use $crate::__private::CowSource as _;
+    let source = $source;
+    source.cow_collect($crate::iter![$exp; for $item in source.cow_iter() $(; $($rest)*)?])
+    }};
+}
+
+/// Create a `HashMap` using Python's dict-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // dict![k(x) => v(x); for x in iter];
+/// // Create a map
+/// let map = dict![x => x*x; for x in 1..10; if x % 3 == 0];
+/// assert_eq!(map.len(), 3);
+/// assert_eq!(map[&6], 36);
+/// // Same as iter![(k, v); ...].collect::<HashMap<_, _>>()
+/// ```
+///
+/// When several items have the same key, the last value is kept. Other maps can be
+/// created from pairs with [`vect`](macro@vect), e.g., `vect![(k, v); for x in iter]`.
+///
+/// # Duplicate keys
+///
+/// A trailing `on duplicate` clause chooses what happens when several items have the same key:
+///
+/// - `last`: the last value is kept, which is the default, like in Python;
+/// - `first`: the first value is kept;
+/// - `error`: a `Result` is returned instead, with the first duplicated key as error;
+/// - a closure: the values are merged, in order, by calling it with the previous and the new value.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // dict![k(x) => v(x); for x in iter; on duplicate policy];
+/// let words = ["apple", "avocado", "banana", "blueberry", "cherry"];
+/// let map = dict![&w[..1] => w; for w in words; on duplicate first];
+/// assert_eq!(map["b"], "banana");
+/// let map = dict![&w[..1] => w.len(); for w in words; on duplicate |a, b| a + b];
+/// assert_eq!(map["a"], 12);
+/// let map = dict![&w[..1] => w; for w in words; on duplicate error];
+/// assert_eq!(map, Err("a"));
+/// let map = dict![w.len() => w; for w in words; if w != "cherry"; on duplicate error];
+/// assert_eq!(map.map(|map| map.len()), Ok(4));
+/// ```
+///
+/// # Default values
+///
+/// A `default` clause fills the keys without a value: the value expression is then an
+/// `Option`, and `None` is replaced by the default expression, which is only evaluated when
+/// needed. With the full domain of keys as the source, lookups in the map never miss.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::collections::HashMap;
+///
+/// let counts = HashMap::from([("a", 3), ("c", 1)]);
+/// // dict![k(x) => v(x); for x in iter; default d];
+/// let map = dict![k => counts.get(k).copied(); for k in ["a", "b", "c"]; default 0];
+/// assert_eq!(map, HashMap::from([("a", 3), ("b", 0), ("c", 1)]));
+/// ```
+///
+/// # Ordered map
+///
+/// A leading `BTreeMap;` creates a `BTreeMap` instead, whose keys are sorted.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // dict![BTreeMap; k(x) => v(x); for x in iter];
+/// let map = dict![BTreeMap; x % 3 => x; for x in 1..10];
+/// assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![(0, 9), (1, 7), (2, 8)]);
+/// ```
+///
+/// # Custom hasher
+///
+/// A leading `as T;` creates any other map implementing [`FromIterator`], e.g., a `HashMap`
+/// whose hasher is built by `S`, which must implement [`BuildHasher`](std::hash::BuildHasher)
+/// and [`Default`]. Duplicate keys can then be handled as above.
+///
+/// Maps of other crates work the same way, e.g., `dict![as IndexMap<_, _>; ...]` keeps the
+/// keys in insertion order like Python's `dict`, with the `indexmap` crate. Only the default
+/// `last` policy is available for such maps.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::collections::HashMap;
+/// use std::hash::{BuildHasherDefault, DefaultHasher};
+///
+/// type Hasher = BuildHasherDefault<DefaultHasher>;
+/// // dict![as HashMap<_, _, S>; k(x) => v(x); for x in iter];
+/// let map = dict![as HashMap<_, _, Hasher>; x => x*x; for x in 1..10; if x % 3 == 0];
+/// assert_eq!(map[&6], 36);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! dict {
+    (as $map:ty; $key:expr => $value:expr; $($body:tt)*) => {
+        $crate::dict!(@scan [$map] [$key] [$value] [] $($body)*)
+    };
+    (HashMap; $($body:tt)*) => {
+        $crate::dict!($($body)*)
+    };
+    (BTreeMap; $key:expr => $value:expr; $($body:tt)*) => {
+        $crate::dict!(@scan [::std::collections::BTreeMap<_, _>] [$key] [$value] [] $($body)*)
+    };
+    // The default value applies to the value expression
+    (@scan $map:tt $key:tt [$value:expr] [$($body:tt)*] ; default $default:expr $(; $($rest:tt)*)?) => {
+        $crate::dict!(@scan $map $key [::core::option::Option::unwrap_or_else($value, || $default)] [$($body)*] $(; $($rest)*)?)
+    };
+    // The duplicate key policy is the last clause
+    (@scan $map:tt $key:tt $value:tt [$($body:tt)*] ; on duplicate $($policy:tt)+) => {
+        $crate::dict!(@policy $map $key $value [$($body)*] $($policy)+)
+    };
+    (@scan $map:tt $key:tt $value:tt [$($body:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::dict!(@scan $map $key $value [$($body)* $next] $($rest)*)
+    };
+    (@scan $map:tt $key:tt $value:tt $body:tt) => {
+        $crate::dict!(@policy $map $key $value $body last)
+    };
+    (@policy [$map:ty] [$key:expr] [$value:expr] [$($body:tt)*] last) => {{
+    ($crate::iter![($key, $value); $($body)*]).collect::<$map>()
+    }};
+    (@policy [$map:ty] [$key:expr] [$value:expr] [$($body:tt)*] first) => {{
+    $crate::__private::dict_first::<$map, _, _, _>($crate::iter![($key, $value); $($body)*])
+    }};
+    (@policy [$map:ty] [$key:expr] [$value:expr] [$($body:tt)*] error) => {{
+    $crate::__private::dict_unique::<$map, _, _, _>($crate::iter![($key, $value); $($body)*])
+    }};
+    (@policy [$map:ty] [$key:expr] [$value:expr] [$($body:tt)*] $merge:expr) => {{
+    $crate::__private::dict_merge::<$map, _, _, _, _>($crate::iter![($key, $value); $($body)*], $merge)
+    }};
+    ($key:expr => $value:expr; $($body:tt)*) => {
+        $crate::dict!(@scan [::std::collections::HashMap<_, _>] [$key] [$value] [] $($body)*)
+    };
+}
+
+/// Create a `HashSet` using Python's set-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // set![f(x); for x in iter];
+/// // Create a set
+/// let set = set![x % 3; for x in 1..10];
+/// assert_eq!(set.len(), 3);
+/// assert!(set.contains(&2));
+/// // Same as iter![...].collect::<HashSet<_>>()
+/// ```
+///
+/// Other sets can be created with [`vect`](macro@vect).
+///
+/// # Ordered set
+///
+/// A leading `BTreeSet;` creates a `BTreeSet` instead, whose values are sorted.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // set![BTreeSet; f(x); for x in iter];
+/// let set = set![BTreeSet; x % 3; for x in 1..10];
+/// assert_eq!(set.into_iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+/// ```
+///
+/// # Custom hasher
+///
+/// A leading `as T;` creates any other set implementing [`FromIterator`], e.g., a `HashSet`
+/// whose hasher is built by `S`, which must implement [`BuildHasher`](std::hash::BuildHasher)
+/// and [`Default`]. Sets of other crates work the same way, e.g., `set![as IndexSet<_>; ...]`
+/// keeps the values in insertion order, with the `indexmap` crate.
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::collections::HashSet;
+/// use std::hash::{BuildHasherDefault, DefaultHasher};
+///
+/// type Hasher = BuildHasherDefault<DefaultHasher>;
+/// // set![as HashSet<_, S>; f(x); for x in iter];
+/// let set = set![as HashSet<_, Hasher>; x % 3; for x in 1..10];
+/// assert_eq!(set.len(), 3);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! set {
+    (as $set:ty; $($body:tt)*) => {{
+    ($crate::iter![$($body)*]).collect::<$set>()
+    }};
+    (HashSet; $($body:tt)*) => {
+        $crate::set!($($body)*)
+    };
+    (BTreeSet; $($body:tt)*) => {{
+    ($crate::iter![$($body)*]).collect::<::std::collections::BTreeSet<_>>()
+    }};
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).collect::<::std::collections::HashSet<_>>()
+    }};
+}
+
+/// Create a `Vec` in a given allocator using Python's list-comprehension style.
+///
+/// Requires the `allocator_api` feature, and a nightly compiler with
+/// `#![feature(allocator_api)]` enabled in the calling crate.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #![feature(allocator_api)]
+/// # #[macro_use] extern crate comptools;
+/// use std::alloc::Global;
+///
+/// // vect_in![alloc; f(x); for x in iter];
+/// // Create a vector in the given allocator
+/// let vec: Vec<u64, Global> = vect_in![Global; x*x; for x in 1..10];
+/// assert_eq!(vec, vec![1, 4, 9, 16, 25, 36, 49, 64, 81]);
+/// // Same as Vec::new_in(alloc) extended with iter![...]
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "allocator_api")]
+#[macro_export]
+macro_rules! vect_in {
+    ($alloc:expr; $($body:tt)*) => {{
+    let mut vec = $crate::__private::Vec::new_in($alloc);
+    vec.extend($crate::iter![$($body)*]);
+    vec
+    }};
+}
+
+/// Create a `Vec` using Python's list-comprehension style, processing chunks of the source in parallel.
+///
+/// The source is split into one chunk per available thread, each chunk is processed on its
+/// own (scoped) thread, and the results are concatenated in the original order. The output
+/// is therefore the same as the one of [`vect`](macro@vect), only computed in parallel.
+///
+/// Only the `for`-first form is supported. Clauses that carry state across the items of the
+/// source, or that apply to the whole comprehension, cannot be evaluated independently on each
+/// chunk, and are rejected: `while`, `state` and `scan` clauses of the first `for` clause, and
+/// `unique`, `rev`, `step`, `skip` and `take` clauses. Inner `for` clauses can still use
+/// `while`, `state` and `scan` clauses, as they restart for each item of the source.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // par_chunks![f(x); for x in iter];
+/// // Create a vector, computing items in parallel
+/// let vec: Vec<u64> = par_chunks![x*x; for x in 1..10; if x % 2 == 1];
+/// assert_eq!(vec, vec![1, 9, 25, 49, 81]);
+/// // Same as vect![...], but in parallel
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! par_chunks {
+    // Clauses are checked first, the level being `[outer]` until an inner `for` clause
+    (@check $head:tt $level:tt ; unique $($rest:tt)*) => {
+        ::core::compile_error!("`unique` clauses are not supported by `par_chunks!`")
+    };
+    (@check $head:tt $level:tt ; rev $($rest:tt)*) => {
+        ::core::compile_error!("`rev` clauses are not supported by `par_chunks!`")
+    };
+    (@check $head:tt $level:tt ; step $($rest:tt)*) => {
+        ::core::compile_error!("`step` clauses are not supported by `par_chunks!`")
+    };
+    (@check $head:tt $level:tt ; skip $($rest:tt)*) => {
+        ::core::compile_error!("`skip` clauses are not supported by `par_chunks!`")
+    };
+    (@check $head:tt $level:tt ; take $($rest:tt)*) => {
+        ::core::compile_error!("`take` clauses are not supported by `par_chunks!`")
+    };
+    (@check $head:tt [outer] ; while $($rest:tt)*) => {
+        ::core::compile_error!("`while` clauses of the first `for` clause are not supported by `par_chunks!`")
+    };
+    (@check $head:tt [outer] ; state $($rest:tt)*) => {
+        ::core::compile_error!("`state` clauses of the first `for` clause are not supported by `par_chunks!`")
+    };
+    (@check $head:tt [outer] ; scan $($rest:tt)*) => {
+        ::core::compile_error!("`scan` clauses of the first `for` clause are not supported by `par_chunks!`")
+    };
+    (@check $head:tt $level:tt ; for $($rest:tt)*) => {
+        $crate::par_chunks!(@check $head [inner] $($rest)*)
+    };
+    (@check $head:tt $level:tt $next:tt $($rest:tt)*) => {
+        $crate::par_chunks!(@check $head $level $($rest)*)
+    };
+    (@check [$threads:expr; $exp:expr; for $item:pat in $iter:expr $(; $($rest:tt)*)?] $level:tt) => {{
+    $crate::__private::par_chunks($iter, $threads, |chunk: $crate::__private::Vec<_>| {
+        ($crate::iter![$exp; for $item in chunk.into_iter() $(; $($rest)*)?]).collect()
+    })
+    }};
+    // Forces the number of chunks
+    (@threads $threads:expr; $exp:expr; for $item:pat in $iter:expr $(; $($rest:tt)*)?) => {
+        $crate::par_chunks!(@check [$threads; $exp; for $item in $iter $(; $($rest)*)?] [outer] $(; $($rest)*)?)
+    };
+    ($exp:expr; for $item:pat in $iter:expr $(; $($rest:tt)*)?) => {
+        $crate::par_chunks!(@threads $crate::__private::available_threads(); $exp; for $item in $iter $(; $($rest)*)?)
+    };
+}
+
+/// Create a `Vec` from fallible values using Python's list-comprehension style.
+///
+/// The expression must return a `Result`. Values are collected until the first error,
+/// which is returned instead of the `Vec`.
+/// With a leading `try`, the expression can instead use `?`, see
+/// [propagating errors](macro@iter#propagating-errors).
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // try_vect![f(x); for x in iter];
+/// // Parse values
+/// let vec = try_vect![x.parse::<u64>(); for x in "1 2 3".split(' ')];
+/// assert_eq!(vec, Ok(vec![1, 2, 3]));
+/// let vec = try_vect![x.parse::<u64>(); for x in "1 x 3".split(' ')];
+/// assert!(vec.is_err());
+/// // Same as iter![...].collect::<Result<Vec<_>, _>>()
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! try_vect {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).collect::<::core::result::Result<$crate::__private::Vec<_>, _>>()
+    }};
+}
+
+/// Create a flattened collection from fallible batches using Python's list-comprehension style.
+///
+/// The expression must return a `Result` of any [`IntoIterator`], e.g., a fallible fetch of
+/// a batch of values. The items of every batch are collected in a single vector, and the
+/// first error stops the iteration and is returned instead.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// fn fetch(page: u64) -> Result<Vec<u64>, String> {
+///     if page < 3 { Ok(vec![page; 2]) } else { Err(format!("page {} not found", page)) }
+/// }
+/// // try_flat_vect![f(x); for x in iter];
+/// // Fetch and flatten batches
+/// let vec: Result<Vec<u64>, _> = try_flat_vect![fetch(x); for x in 0..3];
+/// assert_eq!(vec, Ok(vec![0, 0, 1, 1, 2, 2]));
+/// let vec: Result<Vec<u64>, _> = try_flat_vect![fetch(x); for x in 0..5];
+/// assert_eq!(vec, Err("page 3 not found".to_string()));
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! try_flat_vect {
+    ($($body:tt)*) => {{
+    $crate::__private::try_flat_collect($crate::iter![$($body)*])
+    }};
+}
+
+/// Stream the values of a comprehension through a bounded channel using Python's list-comprehension style.
+///
+/// The comprehension is consumed on a spawned thread, which blocks whenever `bound` items
+/// are waiting to be received, and the [`Receiver`](std::sync::mpsc::Receiver) is returned.
+/// The generated iterator is moved to that thread, so it must be `Send + 'static`: the
+/// expressions cannot borrow local variables.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // channel![bound; f(x); for x in iter];
+/// // Produce values on another thread, at most 4 at a time
+/// let receiver = channel![4; x*x; for x in 1..10];
+/// assert_eq!(receiver.iter().collect::<Vec<u64>>(), vec![1, 4, 9, 16, 25, 36, 49, 64, 81]);
+/// // Same as sending each item of iter![...] through a sync_channel(bound)
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! channel {
+    ($bound:expr; $($body:tt)*) => {{
+    $crate::__private::channel($crate::iter![$($body)*], $bound)
+    }};
+}
+
+/// Write the values of a comprehension to a writer, one per line, using Python's list-comprehension style.
+///
+/// Each value is formatted with [`Display`](std::fmt::Display) and written as soon as it is
+/// computed, so large outputs are streamed without building a collection first. Returns the
+/// first I/O error, if any. The writer is taken by value: pass `&mut writer` to keep it, and
+/// wrap unbuffered writers, like files, in a [`BufWriter`](std::io::BufWriter).
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // write_lines![writer; f(x); for x in iter];
+/// // Write a CSV column to any io::Write
+/// let mut out = Vec::new();
+/// write_lines![&mut out; format!("{x},{}", x * x); for x in 1..4].unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "1,1\n2,4\n3,9\n");
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! write_lines {
+    ($writer:expr; $($body:tt)*) => {{
+    $crate::__private::write_lines($writer, $crate::iter![$($body)*])
+    }};
+}
+
+/// Create an iterator over the nodes of a tree or graph using Python's list-comprehension style.
+///
+/// Nodes are visited from a root, and the children of each node are given by an
+/// expression using it. Traversal is depth-first (preorder) by default, or breadth-first
+/// with `order bfs`. With `unique`, nodes already visited are skipped, so that graphs with
+/// cycles can be traversed. This requires nodes to implement [`Hash`](std::hash::Hash) and [`Eq`].
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// struct Tree {
+///     value: u64,
+///     children: Vec<Tree>,
+/// }
+///
+/// let leaf = |value| Tree { value, children: vec![] };
+/// let root = Tree { value: 1, children: vec![Tree { value: 2, children: vec![leaf(3)] }, leaf(4)] };
+/// // walk![f(node); for node from root; children iter(node)];
+/// let dfs: Vec<u64> = walk![node.value; for node from &root; children node.children.iter()].collect();
+/// assert_eq!(dfs, vec![1, 2, 3, 4]);
+/// let bfs: Vec<u64> = walk![node.value; for node from &root; children node.children.iter(); order bfs].collect();
+/// assert_eq!(bfs, vec![1, 2, 4, 3]);
+/// // Nodes reachable in a graph with cycles
+/// let edges = [vec![1, 2], vec![0], vec![2, 3], vec![]];
+/// let reachable: Vec<usize> = walk![node; for node from 2; children edges[node].clone(); unique].collect();
+/// assert_eq!(reachable, vec![2, 3]);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! walk {
+    (@order []) => {
+        false
+    };
+    (@order [dfs]) => {
+        false
+    };
+    (@order [bfs]) => {
+        true
+    };
+    (@walk $exp:expr; $node:ident; $root:expr; $children:expr; [$($order:ident)?]; $seen:expr) => {
+        $crate::Comprehension::new(
+            $crate::__private::Walk::new($root, $seen, $crate::walk!(@order [$($order)?]), |$node| $children)
+                .map(|$node| $exp),
+        )
+    };
+    ($exp:expr; for $node:ident from $root:expr; children $children:expr $(; order $order:ident)?; unique) => {
+        $crate::walk!(@walk $exp; $node; $root; $children; [$($order)?]; ::std::collections::HashSet::new())
+    };
+    ($exp:expr; for $node:ident from $root:expr; children $children:expr $(; order $order:ident)?) => {
+        $crate::walk!(@walk $exp; $node; $root; $children; [$($order)?]; ())
+    };
+}
+
+/// Declare data-driven tests over cases created using Python's list-comprehension style.
+///
+/// Each function becomes a `#[test]` running its body once per case, with the items of the
+/// comprehension destructured into the arguments. All cases are run, and the test fails
+/// with the list of the failing cases, formatted with [`Debug`](std::fmt::Debug).
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // cases! { fn name(args) in [f(x); for x in iter] { body } }
+/// cases! {
+///     fn square_is_positive(x: i64) in [x; for x in -5..5] {
+///         assert!(x * x >= 0);
+///     }
+///     fn add_commutes(a: i64, b: i64) in [(x, 2 * x); for x in -5..5] {
+///         assert_eq!(a + b, b + a);
+///     }
+/// }
+/// # fn main() {}
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! cases {
+    ($($(#[$attr:meta])* fn $name:ident($($arg:ident: $ty:ty),* $(,)?) in [$($body:tt)*] $block:block)*) => {
+    $(
+    $(#[$attr])*
+    #[test]
+    fn $name() {
+        let mut failures = $crate::__private::Vec::new();
+        for case in $crate::iter![$($body)*] {
+            let label = ::std::format!("{:?}", case);
+            #[allow(unused_parens)]
+            let ($($arg),*): ($($ty),*) = case;
+            let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| $block));
+            if result.is_err() {
+                failures.push(label);
+            }
+        }
+        if !failures.is_empty() {
+            ::std::panic!("{} failed for cases: {}", ::std::stringify!($name), failures.join(", "));
+        }
+    }
+    )*
+    };
+}
+
+/// Declare statics initialized on first use from a comprehension using Python's list-comprehension style.
+///
+/// Each static is wrapped in a [`LazyLock`](std::sync::LazyLock), and its value is created
+/// with [`vect`](macro@vect) the first time it is accessed. Any type implementing
+/// [`FromIterator`] can be used, e.g., maps of pairs. Maps with keys and values clauses are
+/// declared with [`lazy_dict`](macro@lazy_dict) instead.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::collections::HashMap;
+///
+/// // lazy_vect! { static NAME: Type = [f(x); for x in iter]; }
+/// lazy_vect! {
+///     static SQUARES: Vec<u64> = [x*x; for x in 0..10];
+///     pub static ROOTS: HashMap<u64, u64> = [(x*x, x); for x in 0..10];
+/// }
+/// assert_eq!(SQUARES[3], 9);
+/// assert_eq!(ROOTS[&64], 8);
+/// // Same as static NAME: LazyLock<Type> = LazyLock::new(|| vect![...]);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! lazy_vect {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = [$($body:tt)*];)*) => {
+    $(
+    $(#[$attr])*
+    $vis static $name: ::std::sync::LazyLock<$ty> = ::std::sync::LazyLock::new(|| $crate::vect![$($body)*]);
+    )*
+    };
+}
+
+/// Declare maps initialized on first use from a comprehension using Python's dict-comprehension style.
+///
+/// Each static is wrapped in a [`LazyLock`](std::sync::LazyLock), and its value is created
+/// with [`dict`](macro@dict) as the given map type the first time it is accessed, which
+/// makes it convenient for global lookup tables. All the clauses of `dict!` can be used,
+/// except `on duplicate error`, which does not return a map.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// use std::collections::{BTreeMap, HashMap};
+///
+/// // lazy_dict! { static NAME: Type = [k(x) => v(x); for x in iter]; }
+/// lazy_dict! {
+///     static CODES: HashMap<char, u32> = [c => c as u32; for c in 'a'..='z'];
+///     pub static LENGTHS: BTreeMap<usize, &str> = [w.len() => w; for w in ["a", "bb", "cc"]; on duplicate first];
+/// }
+/// assert_eq!(CODES[&'c'], 99);
+/// assert_eq!(LENGTHS[&2], "bb");
+/// // Same as static NAME: LazyLock<Type> = LazyLock::new(|| dict![as Type; ...]);
+/// ```
+///
+/// For more details, refer to the documentation of [`dict`](macro@dict).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! lazy_dict {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = [$($body:tt)*];)*) => {
+    $(
+    $(#[$attr])*
+    $vis static $name: ::std::sync::LazyLock<$ty> = ::std::sync::LazyLock::new(|| $crate::dict![as $ty; $($body)*]);
+    )*
+    };
+}
+
+/// Declare functions returning a comprehension using Python's list-comprehension style.
+///
+/// Each function returns an `impl Iterator<Item = T>`, created with `iter![move ...]`
+/// so that it takes ownership of the arguments. Arguments must therefore be owned,
+/// and those used in several places must be [`Copy`].
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // def_iter! { fn name(args) -> T = [f(x); for x in iter]; }
+/// def_iter! {
+///     fn multiples(n: u64, below: u64) -> u64 = [x; for x in 1..below; if x % n == 0];
+///     /// Powers of `base`, starting from 1.
+///     pub fn powers(base: u64) -> u64 = [base.pow(e); for e in 0..];
+/// }
+/// assert_eq!(multiples(3, 10).collect::<Vec<_>>(), vec![3, 6, 9]);
+/// assert_eq!(powers(2).take(4).collect::<Vec<_>>(), vec![1, 2, 4, 8]);
+/// // Same as fn name(args) -> impl Iterator<Item = T> { iter![move ...] }
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[macro_export]
+macro_rules! def_iter {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $argty:ty),* $(,)?) -> $ty:ty = [$($body:tt)*];)*) => {
+    $(
+    $(#[$attr])*
+    $vis fn $name($($arg: $argty),*) -> impl ::core::iter::Iterator<Item = $ty> {
+        $crate::iter![move $($body)*]
+    }
+    )*
+    };
+}
+
+/// Create a collection using Python's list-comprehension style, memoized by a key.
+///
+/// The first time a given key is seen, the collection is created as with
+/// [`vect`](macro@vect) and stored in a thread-local cache specific to the call site.
+/// Later calls with an equal key return a clone of the cached collection, without
+/// evaluating the comprehension. The key should therefore capture every input the
+/// comprehension depends on. The cache is never cleared.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// fn squares(n: u64) -> Vec<u64> {
+///     // cached_vect![key; f(x); for x in iter];
+///     // Create a collection, only once per value of n
+///     cached_vect![n; x*x; for x in 0..n]
+/// }
+/// assert_eq!(squares(4), vec![0, 1, 4, 9]);
+/// assert_eq!(squares(4), vec![0, 1, 4, 9]); // Cached
+/// assert_eq!(squares(3), vec![0, 1, 4]);
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "std")]
+#[macro_export]
+macro_rules! cached_vect {
+    ($key:expr; $($body:tt)*) => {{
+    ::std::thread_local! {
+        static CACHE: ::std::cell::RefCell<$crate::__private::Cache> = ::std::default::Default::default();
+    }
+    $crate::__private::cached(&CACHE, $key, || ($crate::iter![$($body)*]).collect())
+    }};
+}
+
+/// Create a range with the semantics of Python's `range`, to be used as a comprehension source.
+///
+/// Accepts `range!(stop)`, `range!(start, stop)`, and `range!(start, stop, step)`, where a
+/// negative step counts down. Returns a [`PyRange`](sources::PyRange), see [`range`](sources::range).
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // range!(start, stop, step);
+/// let vec: Vec<i32> = vect![x; for x in range!(10, 0, -2)];
+/// assert_eq!(vec, vec![10, 8, 6, 4, 2]);
+/// let vec: Vec<i32> = vect![x; for x in range!(3)];
+/// assert_eq!(vec, vec![0, 1, 2]);
+/// // Same as range(10, 0, -2) in Python
+/// ```
+#[macro_export]
+macro_rules! range {
+    ($stop:expr $(,)?) => {
+        $crate::sources::range(0, $stop, 1)
+    };
+    ($start:expr, $stop:expr $(,)?) => {
+        $crate::sources::range($start, $stop, 1)
+    };
+    ($start:expr, $stop:expr, $step:expr $(,)?) => {
+        $crate::sources::range($start, $stop, $step)
+    };
+}
+
+/// Unzip a comprehension of pairs into two `Vec`s using Python's list-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // unzip![(a(x), b(x)); for x in iter];
+/// // Split pairs into names and ages
+/// let people = [("alice", 31), ("bob", 25), ("carol", 47)];
+/// let (names, ages) = unzip![(name, age); for (name, age) in people; if age > 30];
+/// assert_eq!(names, vec!["alice", "carol"]);
+/// assert_eq!(ages, vec![31, 47]);
+/// // Same as iter![...].unzip(), or zip(*[...]) in Python
+/// ```
+///
+/// See also [`unzip3`](macro@unzip3) for triplets.
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! unzip {
+    ($($body:tt)*) => {{
+    ($crate::iter![$($body)*]).unzip::<_, _, $crate::__private::Vec<_>, $crate::__private::Vec<_>>()
+    }};
+}
+
+/// Unzip a comprehension of 3-tuples into three `Vec`s using Python's list-comprehension style.
+///
+/// # Basic usage
+///
+/// ```rust
+/// # #[macro_use] extern crate comptools;
+/// // unzip3![(a(x), b(x), c(x)); for x in iter];
+/// // Create an iterator of triplets and split it into three vectors
+/// let (a, b, c) = unzip3![(x, x*x, x*x*x); for x in 1..4];
+/// assert_eq!(a, vec![1, 2, 3]);
+/// assert_eq!(b, vec![1, 4, 9]);
+/// assert_eq!(c, vec![1, 8, 27]);
+/// // Same as iter![...].unzip(), but for triplets
+/// ```
+///
+/// For more details, refer to the documentation of [`iter`](macro@iter).
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! unzip3 {
+    ($($body:tt)*) => {{
+    $crate::__private::unzip3($crate::iter![$($body)*])
+    }};
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    #[test]
+    fn test_vect() {
+        let expected: Vec<u64> = (1..10).map(|x| x * x).collect();
+        let got: Vec<u64> = vect![x*x; for x in 1..10];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_as() {
+        use std::collections::VecDeque;
+        let expected: VecDeque<u64> = (1..10).map(|x| x * x).collect();
+        let got = vect![as VecDeque<_>; x * x; for x in 1..10];
+        assert_eq!(expected, got);
+        assert_eq!(
+            "abc",
+            vect![as String; c; for c in "aXbYc".chars(); if c.is_lowercase()]
+        );
+    }
+    #[test]
+    fn test_vect_if() {
         let expected: Vec<u64> = (1..10).filter(|x| x < &5).map(|x| x * x).collect();
-        let got: Vec<u64> = vect![x*x; for x in 1..10; if x < &5];
+        let got: Vec<u64> = vect![x*x; for x in 1..10; if x < 5];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_if_else() {
+        let expected: Vec<u64> = (1..10).map(|x| if x < 5 { x * x } else { 0 }).collect();
+        let got: Vec<u64> = vect![x*x; for x in 1..10; if x < 5; else 0];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_while() {
+        let expected: Vec<u64> = (1..).take_while(|x| x < &10).collect();
+        let got: Vec<u64> = vect![x; for x in 1..; while x < 10];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_flatten() {
+        let expected: Vec<u64> = (1..5).flat_map(|x| 0..x).collect();
+        let got: Vec<u64> = vect![0..x; for x in 1..5; flatten];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = (1..5)
+            .filter(|x| x % 2 == 0)
+            .flat_map(|x| vec![x; 2])
+            .collect();
+        let got: Vec<u64> = vect![vec![x; 2]; for x in 1..5; if x % 2 == 0; flatten];
+        assert_eq!(expected, got);
+        let expected: Vec<(u64, u64)> = (1..3)
+            .flat_map(|x| (0..x).flat_map(move |y| [(x, y), (y, x)]))
+            .collect();
+        let got: Vec<(u64, u64)> = vect![[(x, y), (y, x)]; for x in 1..3; for y in 0..x; flatten];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_unique() {
+        use std::collections::HashSet;
+        let mut seen = HashSet::new();
+        let expected: Vec<u64> = (1..20).map(|x| x % 7).filter(|x| seen.insert(*x)).collect();
+        let got: Vec<u64> = vect![x % 7; for x in 1..20; unique];
+        assert_eq!(expected, got);
+        let got: Vec<u64> = vect![y; for x in 1..5; for y in 0..x; if y % 2 == 0; unique];
+        assert_eq!(vec![0, 2], got);
+        let got: Vec<char> = vect![w.chars(); for w in "ab ba cab".split(' '); flatten; unique];
+        assert_eq!(vec!['a', 'b', 'c'], got);
+        let unique = true;
+        let got: Vec<u64> = vect![x; for x in 1..3; if unique];
+        assert_eq!(vec![1, 2], got);
+    }
+    #[test]
+    fn test_vect_rev() {
+        let expected: Vec<u64> = (1..10)
+            .filter(|x| x % 3 != 0)
+            .map(|x| x * 2)
+            .rev()
+            .collect();
+        let got: Vec<u64> = vect![x * 2; for x in 1..10; if x % 3 != 0; rev];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = (1..10).rev().collect();
+        let got: Vec<u64> = vect![x; for x in 1..10; rev];
+        assert_eq!(expected, got);
+        let got: Vec<u64> = vect![x % 4; for x in 1..10; rev; unique];
+        assert_eq!(vec![1, 0, 3, 2], got);
+    }
+    #[test]
+    fn test_vect_slicing() {
+        let expected: Vec<u64> = (1..30).map(|x| x * x).skip(1).step_by(2).take(5).collect();
+        let got: Vec<u64> = vect![x * x; for x in 1..30; skip 1; step 2; take 5];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = (1..30).filter(|x| x % 2 == 0).take(5).skip(1).collect();
+        let got: Vec<u64> = vect![x; for x in 1..30; if x % 2 == 0; take 5; skip 1];
+        assert_eq!(expected, got);
+        let n = 2;
+        let got: Vec<(u64, u64)> = vect![(x, y); for x in 0..3; for y in 0..3; step n + 1];
+        assert_eq!(vec![(0, 0), (1, 0), (2, 0)], got);
+    }
+    #[test]
+    fn test_vect_except() {
+        let excluded = vec![2, 3, 5, 7];
+        let expected: Vec<u64> = (1..10)
+            .filter(|x| !excluded.contains(x))
+            .filter(|x| x < &8)
+            .map(|x| x * x)
+            .collect();
+        let got: Vec<u64> = vect![x*x; for x in 1..10; except key *x in excluded.clone(); if x < 8];
+        assert_eq!(expected, got);
+        let got: Vec<u64> = vect![x*x; for x in 1..8; except key x in &excluded];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_state() {
+        let expected: Vec<u64> = (1..10)
+            .scan(0, |total, x| {
+                *total += x;
+                Some(*total)
+            })
+            .collect();
+        let got: Vec<u64> = vect![{ *total += x; *total }; for x in 1..10; state mut total = 0];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = vec![1, 3, 5, 7, 9];
+        let got: Vec<u64> =
+            vect![x; for x in 1..10; state mut odd = false; if { *odd = !*odd; *odd }];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_state_clauses() {
+        // Only the items passing the clauses before the state update it
+        let got: Vec<(u64, u64)> = vect![(y, *n); for x in 1..; let y = x * x; if y % 2 == 1; state mut n = 0; if { *n += 1; *n % 2 == 1 }; take 3];
+        assert_eq!(vec![(1, 1), (25, 3), (81, 5)], got);
+        // Inner states restart for each item of the outer level
+        let got: Vec<u64> = vect![*total; for x in 1..4; for y in 0..x; state mut total = 10 * x; while y < 2; if { *total += y; true }];
+        assert_eq!(vec![10, 20, 21, 30, 31], got);
+        let got: Vec<u64> = vect![{ *last = x; x }; for x in [1, 2, 5, 6]; state mut last = 0; while x <= *last + 2];
+        assert_eq!(vec![1, 2], got);
+    }
+    #[test]
+    fn test_vect_matches() {
+        let values = [Some(1_u64), None, Some(3), Some(4), None];
+        let expected: Vec<usize> = values
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.is_none())
+            .map(|(i, _)| i)
+            .collect();
+        let got: Vec<usize> = vect![i; for i, x in enumerate &values; if matches | (_, None)];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = values
+            .iter()
+            .flatten()
+            .filter(|x| **x > 1)
+            .copied()
+            .collect();
+        let got: Vec<u64> = vect![x.unwrap(); for x in values; if matches Some(n) if *n > 1];
+        assert_eq!(expected, got);
+        let got: Vec<char> = vect![b; for a, b in [(1, 'a'), (2, 'b')]; if matches | (1, _)];
+        assert_eq!(vec!['a'], got);
+        let expected: Vec<(usize, u64)> = vec![(1, 3), (2, 3), (2, 4)];
+        let got: Vec<(usize, u64)> = vect![
+            (i, x.unwrap()); for i in 1..3; for x in values; if matches Some(3..); if x.unwrap() <= i as u64 + 2
+        ];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_matches_fn() {
+        // A function named `matches` is an ordinary condition
+        fn matches(w: &str) -> bool {
+            w.starts_with('a')
+        }
+        let words = ["apple", "banana", "avocado"];
+        let expected: Vec<&str> = words.iter().copied().filter(|w| matches(w)).collect();
+        let got: Vec<&str> = vect![w; for w in words; if matches(w)];
+        assert_eq!(expected, got);
+        let got: Vec<&str> = vect![w; for w in words; if matches(w); if w.len() > 5];
+        assert_eq!(vec!["avocado"], got);
+        let got: Vec<usize> = vect![i; for i, w in enumerate words; if matches(w)];
+        assert_eq!(vec![0, 2], got);
+        let got: Vec<&str> = vect![w; for w in words; if matches(w) && w.len() > 5];
+        assert_eq!(vec!["avocado"], got);
+        let got: Vec<&str> = vect![w; for w in words; if matches(w); else "-"];
+        assert_eq!(vec!["apple", "-", "avocado"], got);
+        let got: Vec<&str> = vect![w; for w in words; if w.len() > 5; if matches(w)];
+        assert_eq!(vec!["avocado"], got);
+    }
+    #[test]
+    fn test_vect_into_iter() {
+        let values = vec![3_u64, 1, 4, 1, 5];
+        let expected: Vec<u64> = values.iter().filter(|x| **x > 1).map(|x| x * 2).collect();
+        let got: Vec<u64> = vect![x * 2; for x in &values; if *x > 1];
+        assert_eq!(expected, got);
+        let got: Vec<u64> = vect![x * 2; for x in values.as_slice(); if *x > 1];
+        assert_eq!(expected, got);
+        let expected: Vec<(u64, char)> = values
+            .iter()
+            .flat_map(|x| ['a', 'b'].into_iter().map(move |c| (*x, c)))
+            .collect();
+        let got: Vec<(u64, char)> = vect![(x, c); for x in values; for c in ['a', 'b']];
+        assert_eq!(expected, got);
+        let expected: usize = 2;
+        let got = count![for x in [1, 2, 3]; if x % 2 == 1];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_scan() {
+        let expected: Vec<u64> = (1..10)
+            .scan(1, |product, x| {
+                *product *= x;
+                Some(*product)
+            })
+            .collect();
+        let got: Vec<u64> = vect![product; for x in 1..10; scan product = 1 => product * x];
+        assert_eq!(expected, got);
+        let expected: Vec<String> = vec!["a".into(), "abc".into()];
+        let got: Vec<String> = vect![s; for c in "abc".chars(); scan s = String::new() => s + &c.to_string(); if c != 'b'];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_scan_clauses() {
+        let expected: Vec<u64> = (1..)
+            .map(|x| x * 2)
+            .scan(0, |total, y| {
+                *total += y;
+                Some(*total)
+            })
+            .skip(1)
+            .take(3)
+            .collect();
+        let got: Vec<u64> =
+            vect![total; for x in 1..; let y = x * 2; scan total = 0 => total + y; skip 1; take 3];
+        assert_eq!(expected, got);
+        let got: Vec<(u64, u64)> =
+            vect![(x, s); for x in 1..4; for y in 1..=x; scan s = 0 => s + y; if s > 1];
+        assert_eq!(vec![(2, 3), (3, 3), (3, 6)], got);
+        let got: Vec<u64> = vect![s; for x in 1..; scan s = 0 => s + x; while s < 10; unique];
+        assert_eq!(vec![1, 3, 6], got);
+    }
+    #[test]
+    fn test_vect_tuple() {
+        let pairs: [(u64, char); 3] = [(1, 'a'), (2, 'b'), (3, 'c')];
+        let expected: Vec<String> = pairs
+            .iter()
+            .filter(|(x, _)| x != &2)
+            .map(|(x, c)| c.to_string().repeat(*x as usize))
+            .collect();
+        let got: Vec<String> =
+            vect![c.to_string().repeat(*x as usize); for x, c in pairs.iter(); if x != &2];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = pairs.iter().map(|(x, _)| x * x).collect();
+        let got: Vec<u64> = vect![x * x; if c != 'd'; for x, c in pairs.into_iter()];
         assert_eq!(expected, got);
     }
     #[test]
-    fn test_vect_if_else() {
-        let expected: Vec<u64> = (1..10).map(|x| if x < 5 { x * x } else { 0 }).collect();
-        let got: Vec<u64> = vect![x*x; for x in 1..10; if x < 5; else 0];
+    fn test_vect_zip() {
+        let a = [1, 2, 3, 4];
+        let b = vec![10, 20, 30];
+        let expected: Vec<u64> = a.iter().zip(b.iter()).map(|(x, y)| x * y).collect();
+        let got: Vec<u64> = vect![x * y; for x, y in &a, &b];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = a
+            .iter()
+            .zip(b.iter())
+            .zip(1..)
+            .filter(|((x, _), _)| **x > 1)
+            .map(|((x, y), z)| x + y + z)
+            .collect();
+        let got: Vec<u64> = vect![x + y + z; for x, y, z in &a, &b, 1..; if *x > 1];
         assert_eq!(expected, got);
     }
     #[test]
-    fn test_vect_while() {
-        let expected: Vec<u64> = (1..).take_while(|x| x < &10).collect();
-        let got: Vec<u64> = vect![x; for x in 1..; while x < &10];
+    fn test_vect_zip_scalar() {
+        let values = [1, 2, 3, 4];
+        let expected: Vec<u64> = values.iter().map(|x| x * 10).filter(|x| x > &15).collect();
+        let got: Vec<u64> = vect![x * k; for x, k in values, scalar 10; if x * k > 15];
+        assert_eq!(expected, got);
+        let got: Vec<String> = vect![format!("{p}{x}"); for p, x in scalar "n".to_owned(), 0..3];
+        assert_eq!(vec!["n0", "n1", "n2"], got);
+        let got: Vec<(u64, char, bool)> =
+            vect![(x, c, b); for x, c, b in values, scalar 'a', scalar true; take 2];
+        assert_eq!(vec![(1, 'a', true), (2, 'a', true)], got);
+    }
+    #[test]
+    fn test_vect_enumerate() {
+        let items = ["a", "b", "c", "d"];
+        let expected: Vec<String> = items
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % 2 == 0)
+            .map(|(i, x)| format!("{i}{x}"))
+            .collect();
+        let got: Vec<String> = vect![format!("{i}{x}"); for i, x in enumerate items; if i % 2 == 0];
+        assert_eq!(expected, got);
+        let got: Vec<String> =
+            vect![format!("{i}{x}"); for (i, &x) in enumerate &items; if i % 2 == 0];
+        assert_eq!(expected, got);
+        let expected: Vec<(usize, usize)> = (0..3)
+            .flat_map(|x| (x..3).enumerate().map(move |(j, _)| (x, j)))
+            .collect();
+        let got: Vec<(usize, usize)> = vect![(x, j); for x in 0..3; for (j, _) in enumerate x..3];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_enumerate_from() {
+        let items = ["a", "b", "c", "d"];
+        let expected: Vec<String> = (1..)
+            .zip(items)
+            .filter(|(i, _)| i % 2 == 0)
+            .map(|(i, x)| format!("{i}{x}"))
+            .collect();
+        let got: Vec<String> =
+            vect![format!("{i}{x}"); for i, x in enumerate items from 1; if i % 2 == 0];
+        assert_eq!(expected, got);
+        let start = 10;
+        let got: Vec<usize> = vect![i; for (i, _) in enumerate items.iter() from start * 2; take 2];
+        assert_eq!(vec![20, 21], got);
+        let got: Vec<(usize, char)> =
+            vect![(i, c); for i, c in enumerate String::from("ab").chars() from 1];
+        assert_eq!(vec![(1, 'a'), (2, 'b')], got);
+        let expected: Vec<(usize, usize)> = (0..3)
+            .flat_map(|x| (x..3).zip(x + 1..).map(move |(_, j)| (x, j)))
+            .collect();
+        let got: Vec<(usize, usize)> =
+            vect![(x, j); for x in 0..3; for (j, _) in enumerate x..3 from x + 1];
+        assert_eq!(expected, got);
+        let got: Vec<usize> =
+            vect![i; for i, x in enumerate items from 1; if x != "b"; for _ in 0..2];
+        assert_eq!(vec![1, 1, 3, 3, 4, 4], got);
+    }
+    #[test]
+    fn test_vect_typed() {
+        let expected: Vec<u64> = (1..10).filter(|x| x < &5).collect();
+        let got: Vec<u64> =
+            vect![x; for x: u64 in (1..10).map(|x| x.to_string().parse().unwrap()); if x < 5];
+        assert_eq!(expected, got);
+        let expected: Vec<usize> = vec![1, 3];
+        let got: Vec<usize> =
+            vect![x.len(); for x: Vec<u8> in vec![vec![0], vec![1; 3]].into_iter()];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_vect_scoping() {
+        let x: u64 = 10;
+        let expected: Vec<u64> = (1..x).filter(|y| y < &5).map(|y| y + x).collect();
+        let got: Vec<u64> = vect![x + 10; for x in 0..x; if x < 5; else 0];
+        assert_eq!(x, 10);
+        let got: Vec<u64> =
+            vect![y + x; for y in (1..x).filter(|x| x < &5); if got.contains(&(y + x))];
+        assert_eq!(expected, got);
+        let x = vec![1, 2, 3];
+        let got: Vec<u64> = vect![x * 2; for x in x.into_iter()];
+        assert_eq!(vec![2, 4, 6], got);
+    }
+    #[test]
+    fn test_comprehension() {
+        struct Holder {
+            iter: crate::Comprehension<Box<dyn Iterator<Item = u64>>>,
+        }
+        let expected: Vec<u64> = (1..10).filter(|x| x % 2 == 0).map(|x| x * x).collect();
+        let holder = Holder {
+            iter: iter![x * x; for x in 1..10; if x % 2 == 0].boxed(),
+        };
+        assert_eq!(expected, holder.iter.collect::<Vec<_>>());
+        let got = iter![x * x; for x in 1..10];
+        assert_eq!((9, Some(9)), got.size_hint());
+        assert_eq!(9, got.len());
+        assert_eq!(vec![81, 64], got.rev().take(2).collect::<Vec<_>>());
+        let mut got = iter![x * x; for x in 1..10];
+        assert_eq!(Some(16), got.nth(3));
+        assert_eq!(Some(64), got.nth_back(1));
+        assert_eq!(
+            vec![25, 36, 49],
+            got.fold(vec![], |acc, x| [acc, vec![x]].concat())
+        );
+        let got = iter![x; for x in 1..5].rfold(0, |acc, x| acc * 10 + x);
+        assert_eq!(4321, got);
+        let _: crate::Comprehension<_> = walk![n; for n from 1; children [n + 1; 0]];
+    }
+    #[test]
+    fn test_join() {
+        let left: Vec<(u64, char)> = vec![(1, 'a'), (2, 'b'), (3, 'c'), (1, 'd')];
+        let right: Vec<(u64, &str)> = vec![(3, "x"), (1, "y"), (4, "z"), (1, "w")];
+        let expected: Vec<(char, &str)> = left
+            .iter()
+            .flat_map(|a| right.iter().filter(|b| a.0 == b.0).map(|b| (a.1, b.1)))
+            .collect();
+        let got: Vec<(char, &str)> =
+            join![(a.1, b.1); for a in left.iter(); for b in right.iter(); on a.0 == b.0].collect();
+        assert_eq!(expected, got);
+        let got: Vec<u64> = join![a + b; for a in 1..10; for b in 1..20; on a * a == *b].collect();
+        assert_eq!(vec![2, 6, 12, 20], got);
+        // Left items are moved into their last match
+        struct Counted<'a>(u64, &'a std::cell::Cell<usize>);
+        impl Clone for Counted<'_> {
+            fn clone(&self) -> Self {
+                self.1.set(self.1.get() + 1);
+                Counted(self.0, self.1)
+            }
+        }
+        let (lclones, rclones) = (std::cell::Cell::new(0), std::cell::Cell::new(0));
+        let left = [1, 2, 3].map(|x| Counted(x, &lclones));
+        let right = [1, 1, 3].map(|x| Counted(x, &rclones));
+        let got: Vec<(u64, u64)> =
+            join![(a.0, b.0); for a in left; for b in right; on a.0 == b.0].collect();
+        assert_eq!(vec![(1, 1), (1, 1), (3, 3)], got);
+        assert_eq!((1, 3), (lclones.get(), rclones.get()));
+    }
+    #[test]
+    fn test_join_strings() {
+        let expected = (1..10)
+            .filter(|x| x % 2 == 0)
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join("-");
+        let got = join_str!["-"; x; for x in 1..10; if x % 2 == 0];
         assert_eq!(expected, got);
+        assert_eq!("", join_str![", "; x; for x in 0..0]);
+        let sep = String::from(" | ");
+        assert_eq!("1 | 2", join_str![&sep; x; for x in 1..3]);
     }
     #[test]
     fn test_sum() {
         let expected: u64 = (1..10).filter(|x| x < &5).map(|x| x * x).sum();
-        let got = sum![x*x; for x in 1..10; if x < &5];
+        let got = sum![x*x; for x in 1..10; if x < 5];
         assert_eq!(expected, got);
     }
     #[test]
     fn test_product() {
         let expected: u64 = (1..10).filter(|x| x < &5).map(|x| x * x).product();
-        let got = product![x*x; for x in 1..10; if x < &5];
+        let got = product![x*x; for x in 1..10; if x < 5];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_fold() {
+        let expected = (1..10).filter(|x| x % 2 == 1).fold(1, |acc, x| acc * 2 + x);
+        let got = fold![acc, x => acc * 2 + x; init 1; for x in 1..10; if x % 2 == 1];
+        assert_eq!(expected, got);
+        let expected = (1..5).map(|x| x * x).fold(Vec::new(), |mut acc, x| {
+            acc.insert(0, x);
+            acc
+        });
+        let got =
+            fold![mut acc, x => { acc.insert(0, x); acc }; init Vec::new(); x * x; for x in 1..5];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_reduce() {
+        let expected = (1..10).filter(|x| x % 2 == 1).reduce(|a, b| a.max(b) * 2);
+        let got = reduce![a, b => a.max(b) * 2; for x in 1..10; if x % 2 == 1];
+        assert_eq!(expected, got);
+        assert_eq!(None, reduce![a, b => a + b; x; for x in 0..10; if x > 10]);
+    }
+    #[test]
+    fn test_max() {
+        let expected = (1..10).filter(|x| x < &5).map(|x| x % 3).max();
+        let got = max![x % 3; for x in 1..10; if x < 5];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_min() {
+        let expected = (1..10).filter(|x| x > &5).map(|x| x % 4).min();
+        let got = min![x % 4; for x in 1..10; if x > 5];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_minmax() {
+        let values: Vec<u64> = (1..10).filter(|x| x > &2).map(|x| x % 4).collect();
+        let expected = values
+            .iter()
+            .copied()
+            .min()
+            .zip(values.iter().copied().max());
+        let got = minmax![x % 4; for x in 1..10; if x > 2];
+        assert_eq!(expected, got);
+        assert_eq!(Some((5, 5)), minmax![x; for x in 5..6]);
+        assert_eq!(None, minmax![x; for x in 5..5]);
+        let got = minmax![(x % 3, x); for x in 1..10];
+        assert_eq!(Some(((0, 3), (2, 8))), got);
+    }
+    #[test]
+    fn test_argmax() {
+        let values = [3, 1, 4, 1, 5, 9, 2, 6, 9];
+        let expected = values
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, x)| **x)
+            .map(|(i, _)| i);
+        let got = argmax![x; for x in values.iter()];
+        assert_eq!(expected, got);
+        assert_eq!(Some(5), got);
+        assert_eq!(None, argmax![x; for x in values.iter(); if *x > 10]);
+    }
+    #[test]
+    fn test_position() {
+        let values = [3, 1, 4, 1, 5, 9, 2, 6];
+        let expected = values.iter().position(|x| x % 2 == 0);
+        let got = position![for x in values.iter(); if x % 2 == 0];
+        assert_eq!(expected, got);
+        assert_eq!(Some(2), got);
+        let got = position![for x in values.iter(); if *x > 1; if x % 2 == 0];
+        assert_eq!(Some(2), got);
+        let got = position![for x in values; let y = x * 2; if y > 8; if y != 10];
+        assert_eq!(Some(5), got);
+        let got = position![for x in values; for y in 0..x; if x + y == 9];
+        assert_eq!(Some(4), got);
+        assert_eq!(None, position![for x in values; if x > 10]);
+    }
+    #[test]
+    fn test_rposition() {
+        let values = [3, 1, 4, 1, 5, 9, 2, 6];
+        let expected = values.iter().rposition(|x| x % 2 == 1);
+        let got = rposition![for x in values.iter(); if x % 2 == 1];
+        assert_eq!(expected, got);
+        assert_eq!(Some(5), got);
+        assert_eq!(
+            Some(3),
+            rposition![for x in values.iter(); if *x > 0; if x * x == 1]
+        );
+        assert_eq!(
+            Some(6),
+            rposition![for x in values; let y = x + 1; if y % 3 == 0]
+        );
+        assert_eq!(None, rposition![for x in values; if x > 10]);
+    }
+    #[test]
+    fn test_argmin() {
+        let values = [3, 1, 4, 1, 5, 9, 2, 6, 9];
+        let expected = values
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, x)| **x)
+            .map(|(i, _)| i);
+        let got = argmin![x; for x in values.iter()];
+        assert_eq!(expected, got);
+        assert_eq!(Some(1), got);
+        assert_eq!(Some(0), argmin![x; for x in values.iter(); if *x > 4]);
+    }
+    #[test]
+    fn test_first() {
+        let expected = (1..10).filter(|x| x % 3 == 0).map(|x| x * 2).next();
+        let got = first![x * 2; for x in 1..10; if x % 3 == 0];
+        assert_eq!(expected, got);
+        assert_eq!(None, first![x; for x in 1..10; while x > 5]);
+    }
+    #[test]
+    fn test_last() {
+        let expected = (1..10).filter(|x| x % 3 == 0).map(|x| x * 2).next_back();
+        let got = last![x * 2; for x in 1..10; if x % 3 == 0];
+        assert_eq!(expected, got);
+        assert_eq!(Some((2, 3)), last![(x, y); for x in 1..3; for y in x..4]);
+    }
+    #[test]
+    fn test_count() {
+        let expected = (1..10).filter(|x| x % 2 == 0).count();
+        let got = count![for x in 1..10; if x % 2 == 0];
+        assert_eq!(expected, got);
+        let got = count![x; for x in 1..10; if x % 2 == 0];
+        assert_eq!(expected, got);
+        assert_eq!(9, count![for _ in 1..10]);
+    }
+    #[test]
+    fn test_any() {
+        let expected = (1..10).filter(|x| x % 2 == 0).any(|x| x > 7);
+        let got = any![x > 7; for x in 1..10; if x % 2 == 0];
+        assert_eq!(expected, got);
+        assert!(!any![x > 9; for x in 1..10]);
+        // Short-circuits on infinite iterators
+        assert!(any![x > 9; for x in 1..]);
+    }
+    #[test]
+    fn test_all() {
+        let expected = (1..10).filter(|x| x % 3 == 0).all(|x| x % 3 == 0);
+        let got = all![x % 3 == 0; for x in 1..10; if x % 3 == 0];
+        assert_eq!(expected, got);
+        // Short-circuits on infinite iterators
+        assert!(!all![x < 9; for x in 1..]);
+    }
+    #[test]
+    fn test_sorted() {
+        let mut expected: Vec<u64> = (1..10).filter(|x| x % 2 == 1).map(|x| x % 5).collect();
+        expected.sort();
+        let got = sorted![x % 5; for x in 1..10; if x % 2 == 1];
+        assert_eq!(expected, got);
+        let mut expected: Vec<u64> = (1..10).collect();
+        expected.sort_by_key(|x| (x % 3, 10 - x));
+        let got = sorted![x; for x in 1..10; by (x % 3, 10 - x)];
+        assert_eq!(expected, got);
+        let calls = std::cell::Cell::new(0);
+        let got = sorted![x; for x in (1..100).rev(); by { calls.set(calls.get() + 1); x }];
+        assert_eq!((1..100).collect::<Vec<u64>>(), got);
+        assert_eq!(99, calls.get());
+    }
+    #[test]
+    fn test_sorted_order() {
+        let mut expected: Vec<u64> = (1..10).map(|x| x % 4).collect();
+        expected.sort_by(|a, b| b.cmp(a));
+        let got = sorted![x % 4; for x in 1..10; desc];
+        assert_eq!(expected, got);
+        let mut expected: Vec<u64> = (1..10).collect();
+        expected.sort_by_key(|x| std::cmp::Reverse(x % 3));
+        let got = sorted![x; for x in 1..10; by desc x % 3];
+        assert_eq!(expected, got);
+        let mut expected: Vec<(u64, u64)> = (1..10).map(|x| (x % 3, x)).collect();
+        expected.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        let got =
+            sorted![(x % 3, x); for x in 1..10; by cmp |a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1))];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_nlargest() {
+        let values = [5_u64, 1, 8, 3, 8, 9, 2, 7];
+        let mut expected: Vec<u64> = values.iter().map(|x| x * 2).collect();
+        expected.sort_by(|a, b| b.cmp(a));
+        expected.truncate(3);
+        let got = nlargest![3; x * 2; for x in values];
+        assert_eq!(expected, got);
+        assert_eq!(
+            vec![9, 8, 8, 7, 5, 3, 2, 1],
+            nlargest![100; x; for x in values]
+        );
+        assert_eq!(Vec::<u64>::new(), nlargest![0; x; for x in values]);
+    }
+    #[test]
+    fn test_nsmallest() {
+        let values = [5_u64, 1, 8, 3, 8, 9, 2, 7];
+        let mut expected: Vec<u64> = values.iter().copied().filter(|x| x % 2 == 1).collect();
+        expected.sort();
+        expected.truncate(2);
+        let got = nsmallest![2; x; for x in values; if x % 2 == 1];
+        assert_eq!(expected, got);
+        let got = nsmallest![2; (x % 3, i); for i, x in enumerate values];
+        assert_eq!(vec![(0, 3), (0, 5)], got);
+    }
+    #[cfg(feature = "allocator_api")]
+    #[test]
+    fn test_vect_in() {
+        use std::alloc::System;
+        let expected: Vec<u64> = (1..10).filter(|x| x < &5).map(|x| x * x).collect();
+        let got: Vec<u64, System> = vect_in![System; x*x; for x in 1..10; if x < 5];
+        assert_eq!(expected, got.to_vec());
+    }
+    #[test]
+    fn test_summary() {
+        let values: Vec<f64> = (1..10).filter(|x| x < &5).map(|x| (x * x) as f64).collect();
+        let got = summary![x*x; for x in 1..10u32; if x < 5];
+        assert_eq!(got.count(), values.len());
+        assert_eq!(got.sum(), values.iter().sum::<f64>());
+        assert_eq!(got.min(), Some(1.0));
+        assert_eq!(got.max(), Some(16.0));
+        assert_eq!(got.mean(), Some(7.5));
+        let got = summary![s.len(); for s in ["a", "bcd"]];
+        assert_eq!(got.sum(), 4.0);
+    }
+    #[test]
+    fn test_bin() {
+        let values: Vec<f64> = (1..10).filter(|x| x < &5).map(|x| (x * x) as f64).collect();
+        let got = bin![3; x*x; for x in 1..10u32; if x < 5];
+        assert_eq!(crate::stats::bin(values.clone(), 3), got);
+        let got = bin![3; x*x; for x in 1..10u64; if x < 5];
+        assert_eq!(crate::stats::bin(values, 3), got);
+    }
+    #[test]
+    fn test_qcut() {
+        let values: Vec<f64> = (1..10).filter(|x| x < &5).map(|x| (x * x) as f64).collect();
+        let got = qcut![3; x*x; for x in 1..10u32; if x < 5];
+        assert_eq!(crate::stats::qcut(values.clone(), 3), got);
+        let got = qcut![3; x*x; for x in 1..10usize; if x < 5];
+        assert_eq!(crate::stats::qcut(values, 3), got);
+    }
+    #[test]
+    fn test_nansum() {
+        let values = [1.5, f64::NAN, -2.0, 0.5];
+        let got = nansum![x * 2.0; for x in values.into_iter(); if x < 1.0];
+        assert_eq!(-3.0, got);
+        let got: f32 = nansum![x; for x in [f32::NAN].into_iter()];
+        assert_eq!(0.0, got);
+        // NaN values fail conditions, so only those left by the clauses are counted
+        let got = nansum![count nan; x * 2.0; for x in values.into_iter(); if x < 1.0];
+        assert_eq!((-3.0, 0), got);
+        let got = nansum![count nan; x * 2.0; for x in values.into_iter()];
+        assert_eq!((0.0, 1), got);
+        let got: (f32, usize) = nansum![count nan; x; for x in [f32::NAN, f32::NAN].into_iter()];
+        assert_eq!((0.0, 2), got);
+    }
+    #[test]
+    fn test_mean() {
+        let values: Vec<f64> = (1..10).filter(|x| x % 2 == 0).map(f64::from).collect();
+        let expected = values.iter().sum::<f64>() / values.len() as f64;
+        let got = mean![x; for x in 1..10_u32; if x % 2 == 0];
+        assert_eq!(Some(expected), got);
+        assert_eq!(None, mean![as f32; x; for x in 1..10_u16; if x > 10]);
+        assert_eq!(Some(2.0), mean![x; for x in 1..4_u64]);
+        assert_eq!(Some(-0.5), mean![x; for x in -2..2_i64]);
+        assert_eq!(Some(1.5_f32), mean![as f32; s.len(); for s in ["a", "bc"]]);
+    }
+    #[test]
+    fn test_nanmean() {
+        let values = [1.5, f64::NAN, -2.0, 0.5];
+        let got = nanmean![x * 2.0; for x in values.into_iter(); if x < 1.0];
+        assert_eq!(Some(-1.5), got);
+        let got: Option<f32> = nanmean![x; for x in [f32::NAN].into_iter()];
+        assert_eq!(None, got);
+        let got = nanmean![count nan; x * 2.0; for x in values.into_iter()];
+        assert_eq!((Some(0.0), 1), got);
+        let got: (Option<f32>, usize) = nanmean![count nan; x; for x in [f32::NAN].into_iter()];
+        assert_eq!((None, 1), got);
+    }
+    #[test]
+    fn test_fmax() {
+        let values = [1.5, f64::NAN, -2.0, 0.5];
+        let got = fmax![x * 2.0; for x in values.into_iter(); if x < 1.0];
+        assert_eq!(Some(1.0), got);
+        let got = fmax![x; for x in [f32::NAN].into_iter()];
+        assert_eq!(None, got);
+    }
+    #[test]
+    fn test_fmin() {
+        let values = [1.5, f64::NAN, -2.0, 0.5];
+        let got = fmin![x * 2.0; for x in values.into_iter(); if x > -1.0];
+        assert_eq!(Some(1.0), got);
+        let got: Option<f64> = fmin![x; for x in [0.0, -0.0].into_iter()];
+        assert!(got.unwrap().is_sign_negative());
+    }
+    #[test]
+    fn test_par_chunks() {
+        let expected: Vec<u64> = (1..1000).filter(|x| x % 3 == 0).map(|x| x * x).collect();
+        let got: Vec<u64> = par_chunks![x*x; for x in 1..1000; if x % 3 == 0];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_par_chunks_threads() {
+        let expected: Vec<u64> =
+            vect![x * y; for x in 1..100; let y = x % 7; if y != 0; for z in 0..x; while z < 3];
+        let got: Vec<u64> = par_chunks![@threads 4; x * y; for x in 1..100; let y = x % 7; if y != 0; for z in 0..x; while z < 3];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = vect![s + x; for x in 1..20; for y in 0..x; scan s = 0 => s + y];
+        let got: Vec<u64> =
+            par_chunks![@threads 3; s + x; for x in 1..20; for y in 0..x; scan s = 0 => s + y];
+        assert_eq!(expected, got);
+        let got: Vec<u64> = par_chunks![@threads 8; x; for x in 0..3];
+        assert_eq!(vec![0, 1, 2], got);
+    }
+    #[test]
+    fn test_try_vect() {
+        let expected: Result<Vec<u64>, ()> = Ok((1..10).filter(|x| x % 2 == 0).collect());
+        let got = try_vect![Ok(x); for x in 1..10; if x % 2 == 0];
+        assert_eq!(expected, got);
+        let mut calls = 0;
+        let got: Result<Vec<u64>, u64> =
+            try_vect![{ calls += 1; if x < 3 { Ok(x) } else { Err(x) } }; for x in 1..10];
+        assert_eq!(Err(3), got);
+        assert_eq!(3, calls);
+    }
+    #[test]
+    fn test_try_expression() {
+        fn parse(items: &[&str]) -> Result<Vec<u64>, std::num::ParseIntError> {
+            try_vect![try x.parse::<u64>()? * 2; for x in items.iter(); if !x.is_empty()]
+        }
+        let expected: Vec<u64> = vec![2, 6];
+        assert_eq!(Ok(expected), parse(&["1", "", "3"]));
+        assert!(parse(&["1", "x"]).is_err());
+        let n = 3;
+        let got: Vec<Result<u64, String>> =
+            iter![move try n * x.ok_or("missing")?; for x in [Some(1), None].into_iter()].collect();
+        assert_eq!(vec![Ok(3), Err("missing".to_owned())], got);
+    }
+    #[test]
+    fn test_try_flat_vect() {
+        let expected: Vec<u64> = (1..10).filter(|x| x < &5).flat_map(|x| 0..x).collect();
+        let got: Result<Vec<u64>, ()> = try_flat_vect![Ok(0..x); for x in 1..10; if x < 5];
+        assert_eq!(Ok(expected), got);
+        let got: Result<Vec<u64>, u64> =
+            try_flat_vect![if x < 3 { Ok(vec![x]) } else { Err(x) }; for x in 1..10];
+        assert_eq!(Err(3), got);
+        let got = try_flat_vect![Ok::<_, ()>([x, x]); for x in 0..2];
+        assert_eq!(Ok(vec![0, 0, 1, 1]), got);
+    }
+    #[test]
+    fn test_channel() {
+        let expected: Vec<u64> = (1..10).filter(|x| x < &5).map(|x| x * x).collect();
+        let got: Vec<u64> = channel![1; x*x; for x in 1..10; if x < 5].iter().collect();
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_write_lines() {
+        let mut out = Vec::new();
+        write_lines![&mut out; x * x; for x in 1..10; if x < 5].unwrap();
+        let expected: String = (1..5).map(|x| format!("{}\n", x * x)).collect();
+        assert_eq!(expected, String::from_utf8(out).unwrap());
+        // Writing stops at the first error
+        let mut buf = [0u8; 5];
+        let got = write_lines![&mut buf[..]; x; for x in ["ab", "cd", "ef"]];
+        assert_eq!(std::io::ErrorKind::WriteZero, got.unwrap_err().kind());
+        assert_eq!(b"ab\ncd", &buf);
+    }
+    #[test]
+    fn test_lazy_vect() {
+        lazy_vect! {
+            static SQUARES: Vec<u64> = [x*x; for x in 1..10; if x < 5];
+        }
+        let expected: Vec<u64> = (1..10).filter(|x| x < &5).map(|x| x * x).collect();
+        assert_eq!(expected, *SQUARES);
+    }
+    #[test]
+    fn test_lazy_dict() {
+        use std::collections::{BTreeMap, HashMap};
+        lazy_dict! {
+            static SQUARES: HashMap<u64, u64> = [x => x*x; for x in 1..10; if x < 5];
+            static FIRSTS: BTreeMap<u64, u64> = [x % 3 => x; for x in 1..10; on duplicate first];
+        }
+        let expected: HashMap<u64, u64> = (1..10).filter(|x| x < &5).map(|x| (x, x * x)).collect();
+        assert_eq!(expected, *SQUARES);
+        assert_eq!(BTreeMap::from([(0, 3), (1, 1), (2, 2)]), *FIRSTS);
+    }
+    #[test]
+    fn test_dict() {
+        use std::collections::HashMap;
+        let expected: HashMap<u64, u64> =
+            (1..10).filter(|x| x % 2 == 0).map(|x| (x, x * x)).collect();
+        let got = dict![x => x * x; for x in 1..10; if x % 2 == 0];
+        assert_eq!(expected, got);
+        let got = dict![x % 3 => x; for x in 1..10];
+        assert_eq!(HashMap::from([(1, 7), (2, 8), (0, 9)]), got);
+    }
+    #[test]
+    fn test_dict_default() {
+        use std::collections::{BTreeMap, HashMap};
+        let counts: HashMap<u64, u64> = HashMap::from([(1, 10), (3, 30)]);
+        let expected: HashMap<u64, u64> = (0..5)
+            .map(|k| (k, counts.get(&k).copied().unwrap_or(k)))
+            .collect();
+        let got = dict![k => counts.get(&k).copied(); for k in 0..5; default k];
+        assert_eq!(expected, got);
+        let got = dict![BTreeMap; k % 2 => counts.get(&k).copied(); for k in 0..4; default 0; on duplicate |a, b| a + b];
+        assert_eq!(BTreeMap::from([(0, 0), (1, 40)]), got);
+        let got = dict![k => counts.get(&k).copied(); for k in 0..5; default 0; if k % 2 == 1];
+        assert_eq!(HashMap::from([(1, 10), (3, 30)]), got);
+    }
+    #[test]
+    fn test_dict_duplicate() {
+        use std::collections::{BTreeMap, HashMap};
+        let expected: HashMap<u64, u64> = HashMap::from([(0, 3), (1, 1), (2, 2)]);
+        let got = dict![x % 3 => x; for x in 1..10; on duplicate first];
+        assert_eq!(expected, got);
+        let expected: HashMap<u64, u64> = HashMap::from([(0, 9), (1, 7), (2, 8)]);
+        let got = dict![x % 3 => x; for x in 1..10; on duplicate last];
+        assert_eq!(expected, got);
+        let expected: BTreeMap<u64, u64> = BTreeMap::from([(0, 18), (1, 12), (2, 15)]);
+        let got = dict![BTreeMap; x % 3 => x; for x in 1..10; on duplicate |a, b| a + b];
+        assert_eq!(expected, got);
+        let got = dict![x % 3 => x; for x in 1..10; if x > 2; on duplicate error];
+        assert_eq!(Err(0), got);
+        let got = dict![BTreeMap; x % 3 => x; for x in 1..4; on duplicate error];
+        assert_eq!(Ok(BTreeMap::from([(0, 3), (1, 1), (2, 2)])), got);
+    }
+    #[test]
+    fn test_set() {
+        use std::collections::HashSet;
+        let expected: HashSet<u64> = (1..10).filter(|x| x % 2 == 0).map(|x| x % 3).collect();
+        let got = set![x % 3; for x in 1..10; if x % 2 == 0];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_ordered_dict_set() {
+        use std::collections::{BTreeMap, BTreeSet, HashSet};
+        let expected: BTreeMap<u64, u64> = (1..10).map(|x| (x % 4, x)).collect();
+        let got = dict![BTreeMap; x % 4 => x; for x in 1..10];
+        assert_eq!(expected, got);
+        let expected: BTreeSet<u64> = (1..10).filter(|x| x % 2 == 0).map(|x| x % 3).collect();
+        let got = set![BTreeSet; x % 3; for x in 1..10; if x % 2 == 0];
+        assert_eq!(expected, got);
+        let got: HashSet<u64> = set![HashSet; x % 3; for x in 1..10; if x % 2 == 0];
+        assert_eq!(expected.into_iter().collect::<HashSet<_>>(), got);
+    }
+    #[test]
+    fn test_pattern() {
+        let pairs = [(1_u64, 'a'), (2, 'b'), (3, 'c')];
+        let expected: Vec<String> = pairs
+            .iter()
+            .filter(|(n, _)| n % 2 == 1)
+            .map(|(n, c)| c.to_string().repeat(*n as usize))
+            .collect();
+        let got: Vec<String> =
+            vect![c.to_string().repeat(n as usize); for &(n, c) in pairs.iter(); if n % 2 == 1];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = pairs
+            .iter()
+            .flat_map(|(n, _)| pairs.iter().map(move |(m, _)| n * m))
+            .collect();
+        let got: Vec<u64> = vect![n * m; for (n, _) in pairs.iter(); for (m, _) in pairs.iter()];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_multiple_if() {
+        let expected: Vec<u64> = (1..30)
+            .filter(|x| x % 2 == 0)
+            .filter(|x| x % 3 == 0)
+            .filter(|x| x % 4 != 0)
+            .collect();
+        let got: Vec<u64> = vect![x; for x in 1..30; if x % 2 == 0; if x % 3 == 0; if x % 4 != 0];
+        assert_eq!(expected, got);
+        let got: Vec<u64> = vect![x; for x, y in (1..30).map(|x| (x, x % 4)); if x % 2 == 0; if x % 3 == 0; if y != 0];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_if_let() {
+        let items = ["1", "x", "3", "4"];
+        let expected: Vec<u64> = items.iter().filter_map(|s| s.parse().ok()).collect();
+        let got: Vec<u64> = vect![n; for s in items.iter(); if let Ok(n) = s.parse()];
+        assert_eq!(expected, got);
+        let expected: Vec<(usize, u64)> = vec![(0, 1), (2, 3)];
+        let got: Vec<(usize, u64)> = vect![(i, n); for (i, s) in items.iter().enumerate(); if let Ok(n) = s.parse::<u64>(); while n < 4];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_let() {
+        let expected: Vec<u64> = (1..10)
+            .map(|x| x * 3)
+            .filter(|y| y % 2 == 0)
+            .map(|y| y * y)
+            .collect();
+        let got: Vec<u64> = vect![y * y; for x in 1..10; let y = x * 3; if y % 2 == 0];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = (1..10).map(|x| x * 3).take_while(|y| *y < 20).collect();
+        let got: Vec<u64> = vect![y; for x in 1..10; let y = x * 3; while y < 20];
+        assert_eq!(expected, got);
+        let expected: Vec<(u64, u64)> = (1..4)
+            .flat_map(|x| (0..x * 2).map(move |z| (x, z)))
+            .collect();
+        let got: Vec<(u64, u64)> = vect![(x, z); for x in 1..4; let y = x * 2; for z in 0..y];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_if_while() {
+        let expected: Vec<u64> = (1..)
+            .take_while(|x| x * x < 100)
+            .filter(|x| x % 3 == 0)
+            .collect();
+        let got: Vec<u64> = vect![x; for x in 1..; while x * x < 100; if x % 3 == 0];
+        assert_eq!(expected, got);
+        let expected: Vec<u64> = (1..)
+            .filter(|x| x % 3 == 0)
+            .take_while(|x| x * x < 100)
+            .collect();
+        let got: Vec<u64> = vect![x; for x in 1..; if x % 3 == 0; while x * x < 100];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_nested() {
+        let expected: Vec<(u64, u64, u64)> = (1..5)
+            .filter(|x| x % 2 == 0)
+            .flat_map(|x| {
+                (1..)
+                    .take_while(|y| y < &4)
+                    .flat_map(move |y| (0..2).map(move |z| (x, y, z)))
+            })
+            .collect();
+        let got: Vec<(u64, u64, u64)> = vect![(x, y, z); for x in 1..5; if x % 2 == 0; for y in 1..; while y < 4; for z in 0..2];
+        assert_eq!(expected, got);
+        let v = &[10, 20, 30];
+        let expected: Vec<u64> = (0..3)
+            .flat_map(|x| (0..3).filter(move |y| y != &x).map(move |y| v[x] + v[y]))
+            .collect();
+        let got: Vec<u64> = vect![v[x] + v[y]; for x in 0..3; for y in 0..3; if y != x];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_dependent() {
+        let n = 6;
+        let expected: Vec<(u64, u64)> = (0..n).flat_map(|i| (0..i).map(move |j| (i, j))).collect();
+        let got: Vec<(u64, u64)> = vect![(i, j); for i in 0..n; for j in 0..i];
+        assert_eq!(expected, got);
+        let words: Vec<String> = vec!["ab".to_owned(), "cde".to_owned()];
+        let expected: Vec<(String, usize)> = words
+            .iter()
+            .flat_map(|w| (0..w.len()).map(move |i| (w.clone(), i)))
+            .collect();
+        let got: Vec<(String, usize)> =
+            vect![(w, i); for w in words.into_iter(); for i in 0..w.len()];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_with() {
+        use std::io::{BufRead, BufReader, Cursor};
+        let expected: Vec<usize> = "a\nbb\nccc".lines().map(str::len).collect();
+        let got: Vec<usize> = vect![line.unwrap().len(); with reader = BufReader::new(Cursor::new("a\nbb\nccc")); for line in reader.lines()];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_array() {
+        let expected: [usize; 4] = core::array::from_fn(|i| i * 2);
+        let got = array![i * 2; for i in 0..4];
+        assert_eq!(expected, got);
+        let expected = [(0, 'a'), (0, 'b'), (1, 'a'), (1, 'b')];
+        let got: [(u8, char); 4] = array![(x, c); for x in 0..2; for c in ['a', 'b']];
+        assert_eq!(expected, got);
+        // Bounds other than literals are collected, and checked at runtime
+        const N: usize = 3;
+        let n = 3;
+        let got: [usize; N] = array![i * 2; for i in 0..n];
+        assert_eq!([0, 2, 4], got);
+        let got: [usize; 3] = array![i * 2; for i in 0..N];
+        assert_eq!([0, 2, 4], got);
+    }
+    #[test]
+    fn test_array_const() {
+        const fn cube(i: usize) -> u64 {
+            (i * i * i) as u64
+        }
+        const CUBES: [u64; 8] = array![const; cube(i); for i in 0..8];
+        let expected: [u64; 8] = core::array::from_fn(cube);
+        assert_eq!(expected, CUBES);
+        const fn halves<const N: usize>() -> [usize; N] {
+            array![const; i / 2; for i in 0..N]
+        }
+        assert_eq!([0, 0, 1, 1, 2], halves::<5>());
+        // The expression is never evaluated for empty arrays
+        const TABLE: [u64; 0] = [];
+        const EMPTY: [u64; 0] = array![const; TABLE[i]; for i in 0..0];
+        assert_eq!(EMPTY, []);
+        assert_eq!([0; 0], halves::<0>());
+        // Values do not need to be `Copy`
+        const NAMES: [Option<&str>; 2] =
+            array![const; if i == 0 { None } else { Some("b") }; for i in 0..2];
+        assert_eq!([None, Some("b")], NAMES);
+    }
+    #[test]
+    #[should_panic]
+    fn test_array_too_short() {
+        let _: [u64; 4] = array![x; for x in 1..10; if x % 3 == 0];
+    }
+    #[test]
+    #[should_panic]
+    fn test_array_too_long() {
+        let _: [u64; 2] = array![x; for x in 1..10; if x % 3 == 0];
+    }
+    #[test]
+    fn test_deque() {
+        use std::collections::VecDeque;
+        let expected: VecDeque<u64> = (1..10).filter(|x| x % 3 != 0).collect();
+        let got = deque![x; for x in 1..10; if x % 3 != 0];
+        assert_eq!(expected, got);
+        let expected: VecDeque<u64> = (1..10).rev().filter(|x| x % 3 != 0).collect();
+        let got = deque![x; for x in 1..10; if x % 3 != 0; rev];
+        assert_eq!(expected, got);
+        let expected: VecDeque<char> = "abcab"
+            .chars()
+            .take(3)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect();
+        let got = deque![c; for c in "abcab".chars(); unique; rev];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_string() {
+        let expected: String = "Hello, World"
+            .chars()
+            .filter(|c| c.is_uppercase())
+            .collect();
+        let got = string![c; for c in "Hello, World".chars(); if c.is_uppercase()];
+        assert_eq!(expected, got);
+        let got = string![format!("{x}."); for x in 1..4];
+        assert_eq!("1.2.3.", got);
+    }
+    #[test]
+    fn test_matrix() {
+        let expected: Vec<Vec<u64>> = (0..4)
+            .filter(|i| i % 2 == 0)
+            .map(|i| (0..3).filter(|j| j != &i).map(|j| i * 10 + j).collect())
+            .collect();
+        let got = matrix![i * 10 + j; for i in 0..4; if i % 2 == 0; for j in 0..3; if j != i];
+        assert_eq!(expected, got);
+        let names = ["ab".to_owned(), "c".to_owned()];
+        let got = matrix![c; for name in names.iter(); for c in name.chars()];
+        assert_eq!(vec![vec!['a', 'b'], vec!['c']], got);
+    }
+    #[test]
+    fn test_cow_vect() {
+        use std::borrow::Cow;
+        let data: Vec<u64> = (1..10).collect();
+        let got = cow_vect![(*x).min(20); for x in &data];
+        assert!(matches!(got, Cow::Borrowed(_)));
+        let got = cow_vect![*x; for x in &data; while x < &5];
+        assert_eq!(got, Cow::<[u64]>::Borrowed(&data[..4]));
+        let expected: Vec<u64> = data.iter().map(|x| x.min(&5)).copied().collect();
+        let got = cow_vect![(*x).min(5); for x in &data];
+        assert_eq!(got, Cow::<[u64]>::Owned(expected));
+        let got = cow_vect![c.to_ascii_uppercase(); for c in "héllo"];
+        assert_eq!(got, Cow::<str>::Owned("HéLLO".to_owned()));
+    }
+    #[test]
+    fn test_walk() {
+        // Binary heap layout: children of n are 2n and 2n + 1
+        let children = |n: u64| (2 * n..2 * n + 2).filter(|c| *c < 8);
+        let got: Vec<u64> = walk![n * 10; for n from 1; children children(n)].collect();
+        assert_eq!(vec![10, 20, 40, 50, 30, 60, 70], got);
+        let got: Vec<u64> = walk![n; for n from 1; children children(n); order bfs].collect();
+        assert_eq!((1..8).collect::<Vec<_>>(), got);
+        let got: Vec<u64> =
+            walk![n; for n from 0; children [(n + 1) % 3, (n + 2) % 3]; order bfs; unique]
+                .collect();
+        assert_eq!(vec![0, 1, 2], got);
+    }
+    cases! {
+        fn test_cases(x: u64, y: u64) in [(x, x * x); for x in 1..10] {
+            assert_eq!(y / x, x);
+        }
+        #[should_panic(expected = "test_cases_failures failed for cases: 2, 3")]
+        fn test_cases_failures(x: u64) in [x; for x in 1..4] {
+            assert!(x < 2);
+        }
+    }
+    #[test]
+    fn test_def_iter() {
+        def_iter! {
+            fn evens(v: Vec<u64>) -> u64 = [x; for x in v.into_iter(); if x % 2 == 0];
+        }
+        let expected: Vec<u64> = (1..10).filter(|x| x % 2 == 0).collect();
+        assert_eq!(expected, evens((1..10).collect()).collect::<Vec<_>>());
+    }
+    #[test]
+    fn test_cached_vect() {
+        use std::cell::Cell;
+        let calls = Cell::new(0);
+        let squares = |n: u64| -> Vec<u64> {
+            cached_vect![n; { calls.set(calls.get() + 1); x*x }; for x in 1..n]
+        };
+        let expected: Vec<u64> = (1..10).map(|x| x * x).collect();
+        assert_eq!(expected, squares(10));
+        assert_eq!(expected, squares(10));
+        assert_eq!(calls.get(), 9);
+        assert_eq!(expected[..4], squares(5));
+        assert_eq!(calls.get(), 13);
+    }
+    #[test]
+    fn test_paginate() {
+        let values: Vec<u64> = (1..10).filter(|x| x < &8).map(|x| x * x).collect();
+        let page = 1;
+        let got = paginate![page (page + 1) of size 3; x*x; for x in 1..10; if x < 8];
+        assert_eq!((values[6..].to_vec(), false), got);
+        let got = paginate![page page of size 3; x*x; for x in 1..10; if x < 8];
+        assert_eq!((values[3..6].to_vec(), true), got);
+        let got = paginate![page 2 of size 4; x*x; for x in 1..10; if x < 8];
+        assert_eq!((vec![], false), got);
+    }
+    #[test]
+    fn test_counter() {
+        use std::collections::HashMap;
+        let expected: HashMap<u64, usize> = HashMap::from([(0, 3), (1, 2), (2, 3)]);
+        let got = counter![x % 3; for x in 1..10; if x != 4];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_custom_hasher() {
+        use std::collections::{HashMap, HashSet};
+        use std::hash::{BuildHasherDefault, DefaultHasher};
+        type Hasher = BuildHasherDefault<DefaultHasher>;
+        let expected: HashMap<u64, u64, Hasher> = (1..10).map(|x| (x % 3, x)).collect();
+        let got = dict![as HashMap<_, _, Hasher>; x % 3 => x; for x in 1..10];
+        assert_eq!(expected, got);
+        let expected: HashMap<u64, u64, Hasher> = (1..4).map(|x| (x % 3, x)).collect();
+        let got = dict![as HashMap<_, _, Hasher>; x % 3 => x; for x in 1..10; on duplicate first];
+        assert_eq!(expected, got);
+        let expected: HashSet<u64, Hasher> = (1..10).map(|x| x % 3).collect();
+        let got = set![as HashSet<_, Hasher>; x % 3; for x in 1..10];
+        assert_eq!(expected, got);
+        let expected: HashMap<u64, usize, Hasher> = [(0, 3), (1, 3), (2, 3)].into_iter().collect();
+        let got = counter![as HashMap<_, usize, Hasher>; x % 3; for x in 1..10];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_group_by() {
+        use std::collections::HashMap;
+        let expected: HashMap<u64, Vec<u64>> =
+            HashMap::from([(0, vec![0, 9]), (1, vec![1, 16]), (2, vec![4])]);
+        let got = group_by![x % 3 => x*x; for x in 0..10; if x < 5];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_group_by_ordered() {
+        use std::collections::BTreeMap;
+        let expected: BTreeMap<u64, Vec<u64>> =
+            BTreeMap::from([(0, vec![0, 9]), (1, vec![1, 16]), (2, vec![4])]);
+        let got = group_by_ordered![x % 3 => x*x; for x in 0..10; if x < 5];
+        assert_eq!(expected, got);
+    }
+    #[test]
+    fn test_pivot() {
+        use std::collections::HashMap;
+        let expected: HashMap<u64, HashMap<bool, u64>> = HashMap::from([
+            (0, HashMap::from([(true, 6), (false, 14)])),
+            (1, HashMap::from([(true, 3), (false, 13)])),
+        ]);
+        let got = pivot![x % 2, x % 3 == 0 => sum x; for x in 1..10; if x < 9];
+        assert_eq!(expected, got);
+        let got = pivot![x % 2, x % 3 == 0 => max x * 2; for x in 1..10; if x < 9];
+        assert_eq!(got[&1][&true], 6);
+        let got = pivot![x % 2, x % 3 == 0 => min x; for x in 1..10; if x < 9];
+        assert_eq!(got[&0][&false], 2);
+        let got = pivot![x % 2, x % 3 == 0 => count; for x in 1..10; if x < 9];
+        assert_eq!(got[&1][&false], 3);
+    }
+    #[test]
+    fn test_try_sum() {
+        let expected: Result<u64, ()> = Ok((1..10).filter(|x| x < &5).map(|x| x * x).sum());
+        let got = try_sum![Ok(x*x); for x in 1..10; if x < 5];
+        assert_eq!(expected, got);
+        let got: Result<u64, u64> = try_sum![if x < 5 { Ok(x) } else { Err(x) }; for x in 1..10];
+        assert_eq!(Err(5), got);
+    }
+    #[test]
+    fn test_try_product() {
+        let expected: Result<u64, ()> = Ok((1..10).filter(|x| x < &5).map(|x| x * x).product());
+        let got = try_product![Ok(x*x); for x in 1..10; if x < 5];
+        assert_eq!(expected, got);
+        let got: Result<u64, u64> =
+            try_product![if x < 5 { Ok(x) } else { Err(x) }; for x in 1..10];
+        assert_eq!(Err(5), got);
+    }
+    #[test]
+    fn test_range() {
+        let expected: Vec<i64> = (1..10).rev().step_by(3).collect();
+        let got: Vec<i64> = vect![x; for x in range!(9, 0, -3)];
+        assert_eq!(expected, got);
+        let expected: Vec<usize> = (2..7).collect();
+        let got: Vec<usize> = vect![x; for x in range!(2, 7)];
+        assert_eq!(expected, got);
+        assert_eq!(0, range!(-3).count());
+    }
+    #[test]
+    fn test_vect_chain() {
+        let head = vec![1_u64, 2];
+        let expected: Vec<u64> = head
+            .iter()
+            .copied()
+            .chain(5..7)
+            .chain([9])
+            .filter(|x| x % 3 != 0)
+            .collect();
+        let got: Vec<u64> = vect![x; for x in chain head.iter().copied(), 5..7, [9]; if x % 3 != 0];
+        assert_eq!(expected, got);
+        let expected: Vec<(u64, char)> = vec![(1, 'a'), (1, 'b'), (1, 'c'), (2, 'a')];
+        let got: Vec<(u64, char)> =
+            vect![(x, c); for x in head; for c in chain "ab".chars(), ['c']; take 4];
+        assert_eq!(expected, got);
+        let got: Vec<char> = vect![c; for i, c in chain [(0, 'a')], vec![(1, 'b')]; if i < 2];
+        assert_eq!(vec!['a', 'b'], got);
+    }
+    #[test]
+    fn test_unzip() {
+        let expected: (Vec<u64>, Vec<bool>) = (1..10)
+            .filter(|x| x % 3 != 0)
+            .map(|x| (x * x, x % 2 == 0))
+            .unzip();
+        let got = unzip![(x * x, x % 2 == 0); for x in 1..10; if x % 3 != 0];
+        assert_eq!(expected, got);
+        let matrix = [[1, 2], [3, 4], [5, 6]];
+        let got = unzip![(row[0], row[1]); for row in matrix];
+        assert_eq!((vec![1, 3, 5], vec![2, 4, 6]), got);
+    }
+    #[test]
+    fn test_unzip3() {
+        let expected: (Vec<u64>, Vec<u64>, Vec<bool>) = (
+            (1..10).filter(|x| x < &5).collect(),
+            (1..10).filter(|x| x < &5).map(|x| x * x).collect(),
+            (1..10).filter(|x| x < &5).map(|x| x % 2 == 0).collect(),
+        );
+        let got = unzip3![(x, x*x, x % 2 == 0); for x in 1..10; if x < 5];
         assert_eq!(expected, got);
+        let (a, b, c) = unzip3![(x, x.to_string(), [x]); for x in 0..3u8];
+        assert_eq!(vec![0, 1, 2], a);
+        assert_eq!(vec!["0", "1", "2"], b);
+        assert_eq!(vec![[0], [1], [2]], c);
     }
 }
@@ -0,0 +1,1130 @@
+//! Iterators meant to be used as sources of comprehensions, i.e., after `for x in`.
+
+use core::cmp::Ordering;
+use core::iter::{FusedIterator, Peekable};
+use core::marker::PhantomData;
+use core::ops::{Range, RangeFrom};
+use core::str::FromStr;
+use core::time::Duration;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::ffi::OsString;
+#[cfg(feature = "std")]
+use std::fs::{self, ReadDir};
+#[cfg(feature = "std")]
+use std::iter::Skip;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "std")]
+use std::time::{Instant, SystemTime};
+#[cfg(feature = "std")]
+use std::{env, io};
+
+/// Pair each item of `iter` with the item preceding it.
+///
+/// The first item is paired with `fill`, and the pairs are `(prev, item)`.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::*;
+///
+/// // Differences between consecutive values
+/// let diffs: Vec<i64> = vect![p.1 - p.0; for p in with_prev([1, 4, 9, 16], 0)];
+/// assert_eq!(diffs, vec![1, 3, 5, 7]);
+/// ```
+pub fn with_prev<I>(iter: I, fill: I::Item) -> WithPrev<I::IntoIter>
+where
+    I: IntoIterator,
+{
+    WithPrev {
+        iter: iter.into_iter(),
+        prev: Some(fill),
+    }
+}
+
+/// Iterator returned by [`with_prev`].
+#[derive(Clone, Debug)]
+pub struct WithPrev<I: Iterator> {
+    iter: I,
+    prev: Option<I::Item>,
+}
+
+impl<I> Iterator for WithPrev<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = (I::Item, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        let prev = self.prev.replace(item.clone())?;
+        Some((prev, item))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I> FusedIterator for WithPrev<I>
+where
+    I: FusedIterator,
+    I::Item: Clone,
+{
+}
+
+/// Pair each item of `iter` with the item following it.
+///
+/// The last item is paired with `fill`, and the pairs are `(item, next)`.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::*;
+///
+/// // Items followed by a larger one
+/// let vec: Vec<u64> = vect![p.0; for p in with_next([3, 1, 2, 5, 4], 0); if p.0 < p.1];
+/// assert_eq!(vec, vec![1, 2]);
+/// ```
+pub fn with_next<I>(iter: I, fill: I::Item) -> WithNext<I::IntoIter>
+where
+    I: IntoIterator,
+{
+    WithNext {
+        iter: iter.into_iter().peekable(),
+        fill: Some(fill),
+    }
+}
+
+/// Iterator returned by [`with_next`].
+#[derive(Clone, Debug)]
+pub struct WithNext<I: Iterator> {
+    iter: Peekable<I>,
+    fill: Option<I::Item>,
+}
+
+impl<I> Iterator for WithNext<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = (I::Item, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        let next = match self.iter.peek() {
+            Some(next) => next.clone(),
+            None => self.fill.take()?,
+        };
+        Some((item, next))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I> FusedIterator for WithNext<I>
+where
+    I: FusedIterator,
+    I::Item: Clone,
+{
+}
+
+/// Extension trait stepping through a range by a fixed amount, with any type that can be [advanced](Advance).
+///
+/// Unlike [`Iterator::step_by`], the step does not need to be a number of items: it is
+/// added to the current value, so ranges of [`Instant`] can be stepped by a
+/// [`Duration`], ranges of floats by a float, and so on.
+///
+/// The iterator ends when the next value would overflow. Like Python's `range`, a bounded
+/// range is empty if the step is negative, and panics if the step is zero. Unbounded ranges
+/// accept any step.
+///
+/// # Example
+///
+#[cfg_attr(feature = "std", doc = "```rust")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// use comptools::*;
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let end = start + Duration::from_secs(120);
+/// // One tick every 30 seconds, end excluded
+/// let ticks: Vec<u64> = vect![(t - start).as_secs(); for t in (start..end).every(Duration::from_secs(30))];
+/// assert_eq!(ticks, vec![0, 30, 60, 90]);
+///
+/// let xs: Vec<f64> = vect![x; for x in (0.0..1.0).every(0.25)];
+/// assert_eq!(xs, vec![0.0, 0.25, 0.5, 0.75]);
+///
+/// // Stops before overflowing
+/// let bytes: Vec<u8> = vect![x; for x in (250..).every(2)];
+/// assert_eq!(bytes, vec![250, 252, 254]);
+/// ```
+pub trait Every<T> {
+    /// Return an iterator over the values of the range, spaced by `step`.
+    fn every<S>(self, step: S) -> Steps<T, S>
+    where
+        T: PartialOrd + Advance<S>;
+}
+
+impl<T> Every<T> for Range<T> {
+    fn every<S>(self, step: S) -> Steps<T, S> {
+        Steps {
+            next: Some(self.start),
+            end: Some(self.end),
+            step,
+        }
+    }
+}
+
+impl<T> Every<T> for RangeFrom<T> {
+    fn every<S>(self, step: S) -> Steps<T, S> {
+        Steps {
+            next: Some(self.start),
+            end: None,
+            step,
+        }
+    }
+}
+
+/// Values that can be advanced by a step of type `S`, as used by [`Every::every`].
+///
+/// Implemented for primitive numbers, stepped by a value of the same type, and for
+/// [`Duration`], [`Instant`] and [`SystemTime`], stepped by a `Duration`.
+///
+/// Calendar dates can be stepped too, by implementing this trait for the date types of
+/// other crates, e.g., with `checked_add_months` for `chrono::NaiveDate` and `chrono::Months`.
+/// Steps of varying length, like months, do not fit in a `Duration`.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::*;
+///
+/// #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+/// struct YearMonth(u16, u8);
+/// struct Months(u8);
+///
+/// impl Advance<Months> for YearMonth {
+///     fn advance(&self, step: &Months) -> Option<Self> {
+///         let months = self.0 as u32 * 12 + (self.1 - 1) as u32 + step.0 as u32;
+///         Some(YearMonth(u16::try_from(months / 12).ok()?, (months % 12) as u8 + 1))
+///     }
+/// }
+///
+/// let quarters: Vec<YearMonth> = vect![m; for m in (YearMonth(2024, 11)..YearMonth(2025, 6)).every(Months(3))];
+/// assert_eq!(quarters, vec![YearMonth(2024, 11), YearMonth(2025, 2), YearMonth(2025, 5)]);
+/// ```
+pub trait Advance<S>: Sized {
+    /// Return the value `step` after `self`, or `None` if it cannot be represented.
+    fn advance(&self, step: &S) -> Option<Self>;
+}
+
+macro_rules! impl_advance {
+    (checked $($t:ty),*) => {
+        $(
+        impl Advance<$t> for $t {
+            fn advance(&self, step: &$t) -> Option<$t> {
+                self.checked_add(*step)
+            }
+        }
+        )*
+    };
+    (float $($t:ty),*) => {
+        $(
+        impl Advance<$t> for $t {
+            fn advance(&self, step: &$t) -> Option<$t> {
+                Some(self + step)
+            }
+        }
+        )*
+    };
+    (duration $($t:ty),*) => {
+        $(
+        impl Advance<Duration> for $t {
+            fn advance(&self, step: &Duration) -> Option<$t> {
+                self.checked_add(*step)
+            }
+        }
+        )*
+    };
+}
+
+impl_advance!(checked u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+impl_advance!(float f32, f64);
+impl_advance!(duration Duration);
+#[cfg(feature = "std")]
+impl_advance!(duration Instant, SystemTime);
+
+/// Iterator returned by [`Every::every`].
+#[derive(Clone, Debug)]
+pub struct Steps<T, S> {
+    next: Option<T>,
+    end: Option<T>,
+    step: S,
+}
+
+impl<T, S> Iterator for Steps<T, S>
+where
+    T: PartialOrd + Advance<S>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let current = self.next.take()?;
+        let next = current.advance(&self.step);
+        if let Some(end) = &self.end {
+            if current >= *end {
+                return None;
+            }
+            // The bound is only reached if values increase
+            match next.as_ref().map(|next| next.partial_cmp(&current)) {
+                Some(Some(Ordering::Greater)) | None => {}
+                Some(Some(Ordering::Equal)) => panic!("step must not be zero"),
+                Some(_) => return None,
+            }
+        }
+        self.next = next;
+        Some(current)
+    }
+}
+
+impl<T, S> FusedIterator for Steps<T, S> where T: PartialOrd + Advance<S> {}
+
+#[cfg(feature = "std")]
+/// Recursively walk the directory `root`, depth-first.
+///
+/// Every file and directory below `root` is yielded, each directory being yielded before its
+/// contents. Symbolic links are yielded but never followed. Errors, e.g., unreadable
+/// directories, are yielded as they are encountered and do not stop the walk; use
+/// [`WalkDir::skip_errors`] to ignore them.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use comptools::*;
+///
+/// // Large Rust files of the current directory
+/// let files: Vec<_> = vect![
+///     p;
+///     for p in walk_dir(".").skip_errors();
+///     if p.extension().is_some_and(|ext| ext == "rs") && p.metadata().is_ok_and(|m| m.len() > 1024)
+/// ];
+/// ```
+pub fn walk_dir<P: AsRef<Path>>(root: P) -> WalkDir {
+    let (stack, error) = match fs::read_dir(root) {
+        Ok(dir) => (vec![dir], None),
+        Err(err) => (vec![], Some(err)),
+    };
+    WalkDir {
+        stack,
+        error,
+        max_depth: usize::MAX,
+    }
+}
+
+#[cfg(feature = "std")]
+/// Iterator returned by [`walk_dir`].
+#[derive(Debug)]
+pub struct WalkDir {
+    stack: Vec<ReadDir>,
+    error: Option<io::Error>,
+    max_depth: usize,
+}
+
+#[cfg(feature = "std")]
+impl WalkDir {
+    /// Skip the entries that could not be read, instead of yielding errors.
+    pub fn skip_errors(self) -> impl Iterator<Item = PathBuf> {
+        self.filter_map(Result::ok)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Iterator for WalkDir {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+        loop {
+            let entry = match self.stack.last_mut()?.next() {
+                Some(entry) => entry,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let path = match entry {
+                Ok(entry) => {
+                    if self.stack.len() < self.max_depth
+                        && entry.file_type().is_ok_and(|t| t.is_dir())
+                    {
+                        match fs::read_dir(entry.path()) {
+                            Ok(dir) => self.stack.push(dir),
+                            Err(err) => self.error = Some(err),
+                        }
+                    }
+                    Ok(entry.path())
+                }
+                Err(err) => Err(err),
+            };
+            return Some(path);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl FusedIterator for WalkDir {}
+
+#[cfg(feature = "std")]
+/// Iterate over the paths matching a glob `pattern`, like Python's `glob.glob`.
+///
+/// The pattern is made of components separated by `/`, in which `*` matches any sequence of
+/// characters, and `?` matches any single character. A `**` component matches any number of
+/// directories, including none. Unlike in shells, `*` also matches names starting with a dot.
+///
+/// Directories are walked with [`walk_dir`] from the longest leading part of the pattern
+/// without wildcards, and no deeper than needed. As with [`walk_dir`], errors are yielded and
+/// do not stop the walk; use [`Glob::skip_errors`] to ignore them.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use comptools::*;
+///
+/// // Large Rust files of the current directory and its subdirectories
+/// let files: Vec<_> = vect![
+///     p;
+///     for p in glob("**/*.rs").skip_errors();
+///     if p.metadata().is_ok_and(|m| m.len() > 1024)
+/// ];
+/// ```
+pub fn glob(pattern: &str) -> Glob {
+    let components: Vec<String> = pattern.split('/').map(String::from).collect();
+    let literal = components
+        .iter()
+        .take_while(|c| !c.contains(['*', '?']))
+        .count();
+    let base = PathBuf::from(components[..literal].join("/"));
+    let pattern = components[literal..].to_vec();
+    if pattern.is_empty() {
+        // Without wildcards, the pattern matches itself, if it exists
+        let exists = fs::symlink_metadata(&base).is_ok();
+        return Glob {
+            walk: None,
+            base: PathBuf::new(),
+            pattern,
+            exact: exists.then_some(base),
+        };
+    }
+    let max_depth = if pattern.iter().any(|c| c == "**") {
+        usize::MAX
+    } else {
+        pattern.len()
+    };
+    let root = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        &base
+    };
+    let walk = WalkDir {
+        max_depth,
+        ..walk_dir(root)
+    };
+    Glob {
+        walk: Some(walk),
+        base,
+        pattern,
+        exact: None,
+    }
+}
+
+#[cfg(feature = "std")]
+/// Iterator returned by [`glob`].
+#[derive(Debug)]
+pub struct Glob {
+    walk: Option<WalkDir>,
+    base: PathBuf,
+    pattern: Vec<String>,
+    exact: Option<PathBuf>,
+}
+
+#[cfg(feature = "std")]
+impl Glob {
+    /// Skip the entries that could not be read, instead of yielding errors.
+    pub fn skip_errors(self) -> impl Iterator<Item = PathBuf> {
+        self.filter_map(Result::ok)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Iterator for Glob {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(path) = self.exact.take() {
+            return Some(Ok(path));
+        }
+        let walk = self.walk.as_mut()?;
+        for path in walk.by_ref() {
+            let path = match path {
+                Ok(path) => path,
+                Err(err) => return Some(Err(err)),
+            };
+            let relative = if self.base.as_os_str().is_empty() {
+                // Paths are relative to the current directory, without a leading `./`
+                path.strip_prefix(".").unwrap_or(&path).to_path_buf()
+            } else {
+                path.strip_prefix(&self.base).unwrap_or(&path).to_path_buf()
+            };
+            let names: Vec<_> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            if glob_matches(&self.pattern, &names) {
+                return Some(Ok(self.base.join(relative)));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(feature = "std")]
+impl FusedIterator for Glob {}
+
+#[cfg(feature = "std")]
+/// Return whether the components of a path match the components of a glob pattern.
+fn glob_matches<P: AsRef<str>, N: AsRef<str>>(pattern: &[P], names: &[N]) -> bool {
+    match pattern.split_first() {
+        None => names.is_empty(),
+        Some((first, rest)) if first.as_ref() == "**" => {
+            (0..=names.len()).any(|i| glob_matches(rest, &names[i..]))
+        }
+        Some((first, rest)) => names.split_first().is_some_and(|(name, names)| {
+            let pattern: Vec<char> = first.as_ref().chars().collect();
+            let name: Vec<char> = name.as_ref().chars().collect();
+            wildcard_matches(&pattern, &name) && glob_matches(rest, names)
+        }),
+    }
+}
+
+#[cfg(feature = "std")]
+/// Return whether `name` matches `pattern`, where `*` and `?` are wildcards.
+fn wildcard_matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|i| wildcard_matches(rest, &name[i..])),
+        Some(('?', rest)) => !name.is_empty() && wildcard_matches(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && wildcard_matches(rest, &name[1..]),
+    }
+}
+
+/// Parse each string of `iter` into a `T`.
+///
+/// Items are `Result`s, so errors can be handled with the comprehension, or ignored
+/// with [`Parsed::skip_errors`].
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::*;
+///
+/// let values: Vec<u64> = vect![x * 2; for x in parse_each::<u64, _>("1 2 x 3".split(' ')).skip_errors()];
+/// assert_eq!(values, vec![2, 4, 6]);
+/// ```
+pub fn parse_each<T, I>(iter: I) -> Parsed<T, I::IntoIter>
+where
+    T: FromStr,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    Parsed {
+        iter: iter.into_iter(),
+        marker: PhantomData,
+    }
+}
+
+#[cfg(feature = "std")]
+/// Parse the command-line arguments, excluding the name of the program, into `T`s.
+///
+/// Same as [`parse_each`] applied to [`std::env::args`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use comptools::*;
+///
+/// // Sum of the numbers passed to the program, ignoring other arguments
+/// let total: f64 = sum![x; for x in parse_args::<f64>().skip_errors()];
+/// ```
+pub fn parse_args<T: FromStr>() -> Parsed<T, Skip<env::Args>> {
+    parse_each(env::args().skip(1))
+}
+
+/// Iterator returned by [`parse_each`] and [`parse_args`].
+#[derive(Clone, Debug)]
+pub struct Parsed<T, I> {
+    iter: I,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T, I> Parsed<T, I>
+where
+    T: FromStr,
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    /// Skip the strings that could not be parsed, instead of yielding errors.
+    pub fn skip_errors(self) -> impl Iterator<Item = T> {
+        self.filter_map(Result::ok)
+    }
+}
+
+impl<T, I> Iterator for Parsed<T, I>
+where
+    T: FromStr,
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    type Item = Result<T, T::Err>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|s| s.as_ref().parse())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+#[cfg(feature = "std")]
+/// Return the environment variables whose name starts with `prefix`, with the prefix removed.
+///
+/// Variables whose name or value is not valid unicode are skipped.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::*;
+/// use std::collections::HashMap;
+///
+/// // Configuration from variables like APP_PORT=8080
+/// let config: HashMap<String, String> = vect![(k.to_lowercase(), v); for k, v in env_vars("APP_")];
+/// ```
+pub fn env_vars(prefix: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    strip_prefixes(env::vars_os(), prefix)
+}
+
+#[cfg(feature = "std")]
+/// Keep the variables of `vars` whose name starts with `prefix`, with the prefix removed.
+fn strip_prefixes<'a, I>(vars: I, prefix: &'a str) -> impl Iterator<Item = (String, String)> + 'a
+where
+    I: IntoIterator<Item = (OsString, OsString)>,
+    I::IntoIter: 'a,
+{
+    vars.into_iter().filter_map(move |(key, value)| {
+        let key = key.into_string().ok()?;
+        let key = key.strip_prefix(prefix)?.to_string();
+        Some((key, value.into_string().ok()?))
+    })
+}
+
+/// Iterate over the indices of the set bits of an integer, from the least significant one.
+///
+/// Indices are `u32`, so they can directly be used in shifts. The iterator is double-ended
+/// and exact-size, which makes it convenient for bitboards and flags.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::*;
+///
+/// let mask = 0b1010_0110u8;
+/// let flags: Vec<u8> = vect![1 << b; for b in bits(mask)];
+/// assert_eq!(flags, vec![0b10, 0b100, 0b10_0000, 0b1000_0000]);
+/// assert_eq!(bits(mask).rev().collect::<Vec<_>>(), vec![7, 5, 2, 1]);
+/// ```
+pub fn bits<T>(mask: T) -> Bits<T> {
+    Bits { mask }
+}
+
+/// Iterator returned by [`bits`].
+#[derive(Clone, Copy, Debug)]
+pub struct Bits<T> {
+    mask: T,
+}
+
+macro_rules! impl_bits {
+    ($($t:ty),*) => {
+        $(
+        impl Iterator for Bits<$t> {
+            type Item = u32;
+
+            fn next(&mut self) -> Option<u32> {
+                if self.mask == 0 {
+                    return None;
+                }
+                let index = self.mask.trailing_zeros();
+                self.mask &= self.mask.wrapping_sub(1);
+                Some(index)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                let len = self.mask.count_ones() as usize;
+                (len, Some(len))
+            }
+        }
+
+        impl DoubleEndedIterator for Bits<$t> {
+            fn next_back(&mut self) -> Option<u32> {
+                if self.mask == 0 {
+                    return None;
+                }
+                let index = <$t>::BITS - 1 - self.mask.leading_zeros();
+                self.mask ^= 1 << index;
+                Some(index)
+            }
+        }
+
+        impl ExactSizeIterator for Bits<$t> {}
+
+        impl FusedIterator for Bits<$t> {}
+        )*
+    };
+}
+
+impl_bits!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// Iterate from `start` to `stop`, excluded, by `step`, with the semantics of Python's `range`.
+///
+/// A negative `step` counts down, and an empty range is returned if `stop` cannot be reached
+/// from `start` in the direction of `step`. See also [`range!`](macro@crate::range), which
+/// also accepts one or two arguments.
+///
+/// # Panics
+///
+/// Panics if `step` is zero.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::*;
+///
+/// let down: Vec<i32> = vect![x; for x in range(10, 0, -3)];
+/// assert_eq!(down, vec![10, 7, 4, 1]);
+/// assert_eq!(range(0, 10, -1).count(), 0);
+/// ```
+pub fn range<T: PyRangeStep>(start: T, stop: T, step: T) -> PyRange<T> {
+    PyRange {
+        next: start,
+        step,
+        len: start.steps_to(stop, step),
+    }
+}
+
+/// Iterator returned by [`range`].
+#[derive(Clone, Copy, Debug)]
+pub struct PyRange<T> {
+    next: T,
+    step: T,
+    len: u128,
+}
+
+/// Integer types accepted by [`range`].
+pub trait PyRangeStep: Copy {
+    /// Return the number of values from `self` to `stop`, excluded, by `step`.
+    #[doc(hidden)]
+    fn steps_to(self, stop: Self, step: Self) -> u128;
+
+    /// Return `self + step`, wrapping around on overflow.
+    #[doc(hidden)]
+    fn step(self, step: Self) -> Self;
+}
+
+macro_rules! impl_py_range {
+    ($($t:ty),*) => {
+        $(
+        impl PyRangeStep for $t {
+            fn steps_to(self, stop: Self, step: Self) -> u128 {
+                assert!(step != 0, "range() step must not be zero");
+                let forward = step > 0;
+                if (forward && self >= stop) || (!forward && self <= stop) {
+                    return 0;
+                }
+                (self.abs_diff(stop) as u128 - 1) / (step.abs_diff(0) as u128) + 1
+            }
+
+            fn step(self, step: Self) -> Self {
+                self.wrapping_add(step)
+            }
+        }
+        )*
+    };
+}
+
+impl_py_range!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl<T: PyRangeStep> Iterator for PyRange<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let next = self.next.step(self.step);
+        Some(core::mem::replace(&mut self.next, next))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match usize::try_from(self.len) {
+            Ok(len) => (len, Some(len)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+}
+
+impl<T: PyRangeStep> FusedIterator for PyRange<T> {}
+
+/// Iterate over the combinations of `K` items of `iter`, like Python's `itertools.combinations`.
+///
+/// Items are collected first, and combinations are arrays of clones of them, in
+/// lexicographic order of their positions. `K` is usually inferred from an array pattern.
+/// Cartesian products do not need a source: use several `for` clauses instead.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::*;
+///
+/// let pairs: Vec<(char, char)> = vect![(a, b); for [a, b] in combinations("abc".chars())];
+/// assert_eq!(pairs, vec![('a', 'b'), ('a', 'c'), ('b', 'c')]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn combinations<I, const K: usize>(iter: I) -> Combinations<I::Item, K>
+where
+    I: IntoIterator,
+{
+    let pool: Vec<I::Item> = iter.into_iter().collect();
+    let done = K > pool.len();
+    Combinations {
+        pool,
+        indices: core::array::from_fn(|i| i),
+        done,
+    }
+}
+
+/// Iterator returned by [`combinations`].
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct Combinations<T, const K: usize> {
+    pool: Vec<T>,
+    indices: [usize; K],
+    done: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Clone, const K: usize> Iterator for Combinations<T, K> {
+    type Item = [T; K];
+
+    fn next(&mut self) -> Option<[T; K]> {
+        if self.done {
+            return None;
+        }
+        let item = core::array::from_fn(|i| self.pool[self.indices[i]].clone());
+        // Increment the rightmost index that is not at its maximum, and reset the next ones
+        let n = self.pool.len();
+        match (0..K).rev().find(|&i| self.indices[i] != i + n - K) {
+            Some(i) => {
+                self.indices[i] += 1;
+                for j in i + 1..K {
+                    self.indices[j] = self.indices[j - 1] + 1;
+                }
+            }
+            None => self.done = true,
+        }
+        Some(item)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Clone, const K: usize> FusedIterator for Combinations<T, K> {}
+
+/// Iterate over the permutations of `K` items of `iter`, like Python's `itertools.permutations`.
+///
+/// Items are collected first, and permutations are arrays of clones of them, in
+/// lexicographic order of their positions. `K` is usually inferred from an array pattern.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::*;
+///
+/// let words: Vec<String> = vect![format!("{a}{b}"); for [a, b] in permutations("abc".chars())];
+/// assert_eq!(words, vec!["ab", "ac", "ba", "bc", "ca", "cb"]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn permutations<I, const K: usize>(iter: I) -> Permutations<I::Item, K>
+where
+    I: IntoIterator,
+{
+    let pool: Vec<I::Item> = iter.into_iter().collect();
+    let n = pool.len();
+    Permutations {
+        indices: (0..n).collect(),
+        cycles: core::array::from_fn(|i| n.saturating_sub(i)),
+        first: true,
+        done: K > n,
+        pool,
+    }
+}
+
+/// Iterator returned by [`permutations`].
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct Permutations<T, const K: usize> {
+    pool: Vec<T>,
+    indices: Vec<usize>,
+    cycles: [usize; K],
+    first: bool,
+    done: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Clone, const K: usize> Iterator for Permutations<T, K> {
+    type Item = [T; K];
+
+    fn next(&mut self) -> Option<[T; K]> {
+        if self.done {
+            return None;
+        }
+        if !core::mem::take(&mut self.first) {
+            // Same algorithm as Python's, where `cycles[i]` counts the remaining
+            // choices for the `i`-th position
+            let n = self.pool.len();
+            let advanced = (0..K).rev().find(|&i| {
+                self.cycles[i] -= 1;
+                if self.cycles[i] == 0 {
+                    self.indices[i..].rotate_left(1);
+                    self.cycles[i] = n - i;
+                    false
+                } else {
+                    self.indices.swap(i, n - self.cycles[i]);
+                    true
+                }
+            });
+            if advanced.is_none() {
+                self.done = true;
+                return None;
+            }
+        }
+        Some(core::array::from_fn(|i| self.pool[self.indices[i]].clone()))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Clone, const K: usize> FusedIterator for Permutations<T, K> {}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_prev() {
+        let got: Vec<_> = with_prev(1..5, 0).collect();
+        assert_eq!(got, vec![(0, 1), (1, 2), (2, 3), (3, 4)]);
+        assert_eq!(with_prev(0..0, 0).next(), None);
+    }
+    #[test]
+    fn test_with_next() {
+        let got: Vec<_> = with_next(1..5, 0).collect();
+        assert_eq!(got, vec![(1, 2), (2, 3), (3, 4), (4, 0)]);
+        assert_eq!(with_next(0..0, 0).next(), None);
+    }
+    #[test]
+    fn test_every() {
+        let got: Vec<_> = (1..10).every(4).collect();
+        assert_eq!(got, vec![1, 5, 9]);
+        let got: Vec<_> = (1.0..2.0).every(0.5).collect();
+        assert_eq!(got, vec![1.0, 1.5]);
+        let got: Vec<_> = (5..).every(-2).take(4).collect();
+        assert_eq!(got, vec![5, 3, 1, -1]);
+        assert_eq!((1..1).every(1).next(), None);
+    }
+    #[test]
+    fn test_every_overflow() {
+        let got: Vec<u8> = crate::vect![x; for x in (250u8..255).every(2)];
+        assert_eq!(got, vec![250, 252, 254]);
+        let got: Vec<u8> = (250u8..).every(3).collect();
+        assert_eq!(got, vec![250, 253]);
+        let got: Vec<i8> = (-126i8..).every(-1).collect();
+        assert_eq!(got, vec![-126, -127, -128]);
+        let got: Vec<u64> = (u64::MAX - 1..u64::MAX).every(5).collect();
+        assert_eq!(got, vec![u64::MAX - 1]);
+    }
+    #[test]
+    fn test_every_empty_step() {
+        assert_eq!((0..10).every(-1).next(), None);
+        assert_eq!((0.0..1.0).every(-0.5).next(), None);
+        assert_eq!((0.0..1.0).every(f64::NAN).next(), None);
+        assert_eq!((5..).every(0).take(2).collect::<Vec<_>>(), vec![5, 5]);
+    }
+    #[test]
+    #[should_panic(expected = "step must not be zero")]
+    fn test_every_zero_step() {
+        (0..10).every(0).next();
+    }
+    #[test]
+    fn test_walk_dir() {
+        let root = std::env::temp_dir().join(format!("comptools-walk-{}", std::process::id()));
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/c.txt"), "").unwrap();
+        fs::write(root.join("d.txt"), "").unwrap();
+
+        let mut got: Vec<_> = walk_dir(&root)
+            .map(|p| p.unwrap().strip_prefix(&root).unwrap().to_path_buf())
+            .collect();
+        got.sort();
+        let expected: Vec<PathBuf> = ["a", "a/b", "a/b/c.txt", "d.txt"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(got, expected);
+
+        fs::remove_dir_all(&root).unwrap();
+        assert!(walk_dir(&root).next().unwrap().is_err());
+        assert_eq!(walk_dir(&root).skip_errors().next(), None);
+    }
+    #[test]
+    fn test_glob() {
+        let root = std::env::temp_dir().join(format!("comptools-glob-{}", std::process::id()));
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        for file in ["a/x.rs", "a/b/y.rs", "a/b/z.txt", "w.rs"] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let root_str = root.to_str().unwrap();
+        let glob_sorted = |pattern: &str| {
+            let mut got: Vec<_> = glob(&format!("{root_str}/{pattern}"))
+                .map(|p| p.unwrap().strip_prefix(&root).unwrap().to_path_buf())
+                .collect();
+            got.sort();
+            got
+        };
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+        assert_eq!(glob_sorted("*.rs"), paths(&["w.rs"]));
+        assert_eq!(
+            glob_sorted("**/*.rs"),
+            paths(&["a/b/y.rs", "a/x.rs", "w.rs"])
+        );
+        assert_eq!(glob_sorted("a/*/?.*"), paths(&["a/b/y.rs", "a/b/z.txt"]));
+        assert_eq!(
+            glob_sorted("a/**"),
+            paths(&["a/b", "a/b/y.rs", "a/b/z.txt", "a/x.rs"])
+        );
+        assert_eq!(glob_sorted("a/x.rs"), paths(&["a/x.rs"]));
+        assert_eq!(glob_sorted("a/none.rs"), paths(&[]));
+
+        fs::remove_dir_all(&root).unwrap();
+        assert!(glob(&format!("{root_str}/*")).next().unwrap().is_err());
+        assert_eq!(glob(&format!("{root_str}/*")).skip_errors().next(), None);
+    }
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches(&["*.rs"], &["main.rs"]));
+        assert!(!glob_matches(&["*.rs"], &["src", "main.rs"]));
+        assert!(glob_matches(&["**", "*.rs"], &["main.rs"]));
+        assert!(glob_matches(
+            &["src", "**", "m?in.*"],
+            &["src", "a", "b", "main.rs"]
+        ));
+        assert!(!glob_matches(&["?"], &["ab"]));
+        assert!(glob_matches(&["a*b*c"], &["aXbYbc"]));
+    }
+    #[test]
+    fn test_parse_each() {
+        let got: Vec<_> = parse_each::<u8, _>(["1", "x", "300"]).collect();
+        assert_eq!(got[0], Ok(1));
+        assert!(got[1].is_err());
+        assert!(got[2].is_err());
+        let got: Vec<i32> = parse_each(vec!["-1".to_string(), "x".to_string()])
+            .skip_errors()
+            .collect();
+        assert_eq!(got, vec![-1]);
+    }
+    #[test]
+    fn test_env_vars() {
+        let vars = [
+            ("APP_NAME", "value"),
+            ("APP_", "empty"),
+            ("OTHER_NAME", "other"),
+            ("app_name", "lowercase"),
+        ]
+        .map(|(key, value)| (OsString::from(key), OsString::from(value)));
+        let got: Vec<_> = strip_prefixes(vars, "APP_").collect();
+        let expected = vec![
+            ("NAME".to_string(), "value".to_string()),
+            (String::new(), "empty".to_string()),
+        ];
+        assert_eq!(got, expected);
+    }
+    #[test]
+    fn test_bits() {
+        assert_eq!(bits(0u64).next(), None);
+        assert_eq!(bits(0b1011u16).collect::<Vec<_>>(), vec![0, 1, 3]);
+        assert_eq!(bits(u128::MAX).len(), 128);
+        assert_eq!(bits(-1i8).collect::<Vec<_>>(), (0..8).collect::<Vec<_>>());
+        assert_eq!(bits(i64::MIN).next_back(), Some(63));
+        let mut iter = bits(0b1001_0110u32);
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next_back(), Some(7));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![2, 4]);
+    }
+    #[test]
+    fn test_range() {
+        assert_eq!(range(0, 5, 2).collect::<Vec<_>>(), vec![0, 2, 4]);
+        assert_eq!(range(5, 0, -2).collect::<Vec<_>>(), vec![5, 3, 1]);
+        assert_eq!(range(0u8, 255, 100).collect::<Vec<_>>(), vec![0, 100, 200]);
+        assert_eq!(range(i8::MIN, i8::MAX, i8::MAX).count(), 3);
+        assert_eq!(range(3, 3, 1).size_hint(), (0, Some(0)));
+        assert_eq!(range(0, 3, -1).next(), None);
+    }
+    #[test]
+    #[should_panic]
+    fn test_range_zero_step() {
+        let _ = range(0, 3, 0);
+    }
+    #[test]
+    fn test_combinations() {
+        let got: Vec<[u8; 2]> = combinations(0..4).collect();
+        assert_eq!(got, vec![[0, 1], [0, 2], [0, 3], [1, 2], [1, 3], [2, 3]]);
+        assert_eq!(combinations::<_, 3>(0..3).count(), 1);
+        assert_eq!(combinations::<_, 0>(0..3).count(), 1);
+        assert_eq!(combinations::<_, 4>(0..3).count(), 0);
+        assert_eq!(combinations::<_, 3>(0..10).count(), 120);
+    }
+    #[test]
+    fn test_permutations() {
+        let got: Vec<[u8; 3]> = permutations(0..3).collect();
+        assert_eq!(
+            got,
+            vec![
+                [0, 1, 2],
+                [0, 2, 1],
+                [1, 0, 2],
+                [1, 2, 0],
+                [2, 0, 1],
+                [2, 1, 0]
+            ]
+        );
+        assert_eq!(permutations::<_, 2>(0..4).count(), 12);
+        assert_eq!(permutations::<_, 0>(0..3).count(), 1);
+        assert_eq!(permutations::<_, 4>(0..3).count(), 0);
+    }
+}
@@ -0,0 +1,237 @@
+//! Statistics computed over comprehensions.
+
+pub use crate::__private::IntoFloat;
+
+/// Descriptive statistics of a sequence of numbers, computed in a single pass.
+///
+/// The mean and the variance are updated with Welford's algorithm, which is numerically
+/// stable. A [`Summary`] is usually created with [`summary`](macro@crate::summary), but it
+/// can also be collected from any iterator of primitive numbers, converted with [`IntoFloat`].
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::stats::Summary;
+///
+/// let summary: Summary = [2, 4, 4, 4, 5, 5, 7, 9].into_iter().collect();
+/// assert_eq!(summary.count(), 8);
+/// assert_eq!(summary.sum(), 40.0);
+/// assert_eq!(summary.mean(), Some(5.0));
+/// assert_eq!(summary.std(), Some((32.0_f64 / 7.0).sqrt()));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    count: usize,
+    sum: f64,
+    min: f64,
+    max: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Summary {
+    /// Create an empty summary.
+    pub fn new() -> Self {
+        Self {
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    /// Add a value to the summary.
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Return the number of values.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Return the sum of the values.
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// Return the smallest value, if any.
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    /// Return the largest value, if any.
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Return the arithmetic mean of the values, if any.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.mean)
+    }
+
+    /// Return the sample variance of the values, if there are at least two.
+    pub fn var(&self) -> Option<f64> {
+        (self.count > 1).then(|| self.m2 / (self.count - 1) as f64)
+    }
+
+    /// Return the sample standard deviation of the values, if there are at least two.
+    pub fn std(&self) -> Option<f64> {
+        self.var().map(f64::sqrt)
+    }
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: IntoFloat<f64>> Extend<T> for Summary {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value.into_float());
+        }
+    }
+}
+
+impl<T: IntoFloat<f64>> FromIterator<T> for Summary {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut summary = Self::new();
+        summary.extend(iter);
+        summary
+    }
+}
+
+/// Assign each value to one of `bins` equal-width bins spanning the range of the values.
+///
+/// Returns the `(value, bin)` pairs, in the original order. Bins are closed on the right,
+/// except the first one which also includes the smallest value. Values must not be NaN.
+///
+/// # Panics
+///
+/// Panics if `bins` is zero.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::stats::bin;
+///
+/// let bins = bin(vec![0.0, 1.0, 2.5, 4.0], 2);
+/// assert_eq!(bins, vec![(0.0, 0), (1.0, 0), (2.5, 1), (4.0, 1)]);
+/// ```
+pub fn bin(values: Vec<f64>, bins: usize) -> Vec<(f64, usize)> {
+    assert!(bins > 0, "the number of bins must be positive");
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let width = (max - min) / bins as f64;
+    let edges: Vec<f64> = (1..bins).map(|i| min + width * i as f64).collect();
+    digitize(values, &edges)
+}
+
+/// Assign each value to one of `bins` quantile-based bins, holding about the same number of values.
+///
+/// Returns the `(value, bin)` pairs, in the original order. Bin edges are the quantiles of the
+/// values, computed with linear interpolation, and bins are closed on the right as with [`bin`],
+/// so equal values always share the same bin. Values must not be NaN.
+///
+/// # Panics
+///
+/// Panics if `bins` is zero.
+///
+/// # Example
+///
+/// ```rust
+/// use comptools::stats::qcut;
+///
+/// let bins = qcut(vec![1.0, 100.0, 2.0, 3.0], 2);
+/// assert_eq!(bins, vec![(1.0, 0), (100.0, 1), (2.0, 0), (3.0, 1)]);
+/// ```
+pub fn qcut(values: Vec<f64>, bins: usize) -> Vec<(f64, usize)> {
+    assert!(bins > 0, "the number of bins must be positive");
+    if values.is_empty() {
+        return values.into_iter().map(|value| (value, 0)).collect();
+    }
+    let mut sorted = values.clone();
+    sorted.sort_by(f64::total_cmp);
+    let edges: Vec<f64> = (1..bins)
+        .map(|i| quantile(&sorted, i as f64 / bins as f64))
+        .collect();
+    digitize(values, &edges)
+}
+
+/// Return the `q`-th quantile of non-empty sorted values, with linear interpolation.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let pos = q * (sorted.len() - 1) as f64;
+    let (lower, frac) = (pos.floor() as usize, pos.fract());
+    match sorted.get(lower + 1) {
+        Some(upper) if frac > 0.0 => sorted[lower] + (upper - sorted[lower]) * frac,
+        _ => sorted[lower],
+    }
+}
+
+/// Pair each value with the number of inner bin edges strictly lower than it.
+fn digitize(values: Vec<f64>, edges: &[f64]) -> Vec<(f64, usize)> {
+    values
+        .into_iter()
+        .map(|value| (value, edges.partition_point(|edge| *edge < value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let summary: Summary = [1.0, 2.0, 3.0, 4.0].into_iter().collect();
+        assert_eq!(summary.count(), 4);
+        assert_eq!(summary.sum(), 10.0);
+        assert_eq!(summary.min(), Some(1.0));
+        assert_eq!(summary.max(), Some(4.0));
+        assert_eq!(summary.mean(), Some(2.5));
+        assert_eq!(summary.var(), Some(5.0 / 3.0));
+    }
+    #[test]
+    fn test_summary_empty() {
+        let summary = Summary::default();
+        assert_eq!(summary.count(), 0);
+        assert_eq!(summary.sum(), 0.0);
+        assert_eq!(summary.min(), None);
+        assert_eq!(summary.mean(), None);
+        assert_eq!(summary.std(), None);
+    }
+    #[test]
+    fn test_summary_u64() {
+        let summary: Summary = [u64::MAX, 0].into_iter().collect();
+        assert_eq!(summary.max(), Some(u64::MAX as f64));
+        assert_eq!(summary.min(), Some(0.0));
+    }
+    #[test]
+    fn test_bin() {
+        let got = bin(vec![3.0, 0.0, 10.0, 5.0, 9.9], 4);
+        assert_eq!(got, vec![(3.0, 1), (0.0, 0), (10.0, 3), (5.0, 1), (9.9, 3)]);
+        let got = bin(vec![1.0, 1.0], 3);
+        assert_eq!(got, vec![(1.0, 0), (1.0, 0)]);
+        assert_eq!(bin(vec![], 3), vec![]);
+    }
+    #[test]
+    fn test_qcut() {
+        let got = qcut(vec![5.0, 1.0, 2.0, 3.0, 4.0, 6.0], 3);
+        assert_eq!(
+            got,
+            vec![(5.0, 2), (1.0, 0), (2.0, 0), (3.0, 1), (4.0, 1), (6.0, 2)]
+        );
+        let got = qcut(vec![1.0, 1.0, 1.0, 2.0], 2);
+        assert_eq!(got, vec![(1.0, 0), (1.0, 0), (1.0, 0), (2.0, 1)]);
+        assert_eq!(qcut(vec![], 3), vec![]);
+    }
+}
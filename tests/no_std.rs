@@ -0,0 +1,48 @@
+//! Comprehensions expanded in a `no_std` crate must only use `core`, and `alloc` for the
+//! macros collecting into `Vec`.
+#![no_std]
+
+use comptools::*;
+
+#[test]
+fn test_core() {
+    let sum: u64 = sum![x * y; for x in 1..4; if x != 2; for y in 0..x; while y < 2];
+    assert_eq!(sum, 3);
+    let product: u32 = product![a + b; for a, b in 1..4, 2..5; rev];
+    assert_eq!(product, 105);
+    assert_eq!(max![i * x; for i, x in enumerate [3, 1, 2]], Some(4));
+    assert_eq!(
+        count![for x in range!(10, 0, -3); let y = x % 2; if y == 0],
+        2
+    );
+    assert!(any![c == 'b'; for c in "abc".chars(); skip 1; take 1]);
+    assert_eq!(
+        first![y; for x in 1_u32..; if let Some(y) = x.checked_sub(3)],
+        Some(0)
+    );
+    assert_eq!(max![x * y; for x in [1, 2]; for y in &[3, 4]], Some(8));
+    assert_eq!(array![i + 1; for i in 0..3], [1, 2, 3]);
+    const TABLE: [u8; 3] = array![const; 1 << i; for i in 0..3];
+    assert_eq!(TABLE, [1, 2, 4]);
+    let odd: [u32; 2] = array![x; for x in 0..5; if x % 2 == 1];
+    assert_eq!(odd, [1, 3]);
+    assert_eq!(fold![acc, x => acc * 10 + x; init 0; for x in 1..4], 123);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_alloc() {
+    extern crate alloc;
+    use alloc::vec::Vec;
+    use core::num::ParseIntError;
+
+    let vec: Vec<u64> = vect![x * x; for x in 1..5; step 2];
+    assert_eq!(vec, [1, 9]);
+    let sorted = sorted![x % 3; for x in 1..5; desc];
+    assert_eq!(sorted, [2, 1, 1, 0]);
+    assert_eq!(deque![x; for x in 1..4; rev], [3, 2, 1]);
+    let parsed: Result<Vec<u8>, ParseIntError> =
+        try_vect![try x.parse::<u8>()?; for x in "1 2".split(' ')];
+    assert_eq!(parsed, Ok(alloc::vec![1, 2]));
+    assert_eq!(join_str![", "; x * x; for x in 1..4], "1, 4, 9");
+}